@@ -1,12 +1,13 @@
 //! Configuration struct for report generation.
 use crate::extension::ConfigExtension;
+use crate::rounding::RoundingRule;
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Struct for template configuration.
 ///
-/// At present, configuration contains a scale and an optional path to a [datastore][`yaml_datastore`].
+/// At present, configuration contains a scale and optional paths to [datastores][`yaml_datastore`].
 ///
 /// Construct via [`ConfigBuilder`] or [`default()`][`Self::default`].
 ///
@@ -20,11 +21,16 @@ use std::path::PathBuf;
 /// let config = Config::builder().scale(2.0).datastore_path("db").build();
 /// ```
 pub struct Config {
-    pub(crate) scale: f64,
-    pub(crate) datastore_path: Option<PathBuf>,
+    pub(crate) scaling: Scaling,
+    pub(crate) datastore_paths: Vec<PathBuf>,
     pub(crate) base_path: Option<PathBuf>,
     pub(crate) aisle_path: Option<PathBuf>,
+    pub(crate) store_aisle_paths: BTreeMap<String, PathBuf>,
     pub(crate) pantry_path: Option<PathBuf>,
+    pub(crate) templates: Option<TemplateSource>,
+    pub(crate) strict: bool,
+    pub(crate) practical_rounding: bool,
+    pub(crate) rounding_rules: BTreeMap<String, RoundingRule>,
     pub(crate) extensions: Vec<Box<dyn ConfigExtension>>,
     pub(crate) extra_context: BTreeMap<String, Value>,
 }
@@ -33,11 +39,16 @@ impl Default for Config {
     /// Return a default [`Config`] with a scale of 1, no datastore path, aisle path, pantry path, and base path set to the current working directory.
     fn default() -> Self {
         Self {
-            scale: 1.0,
-            datastore_path: None,
+            scaling: Scaling::Factor(1.0),
+            datastore_paths: Vec::new(),
             base_path: std::env::current_dir().ok(),
             aisle_path: None,
+            store_aisle_paths: BTreeMap::new(),
             pantry_path: None,
+            templates: None,
+            strict: false,
+            practical_rounding: false,
+            rounding_rules: BTreeMap::new(),
             extensions: Vec::new(),
             extra_context: BTreeMap::new(),
         }
@@ -77,38 +88,117 @@ impl Config {
     }
 }
 
+/// How the recipe being rendered is scaled.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Scaling {
+    /// Multiply quantities by this factor.
+    Factor(f64),
+    /// Scale to this many servings, using the recipe's `servings` metadata.
+    Servings(u32),
+    /// Scale to this yield, using the recipe's `yield` metadata (e.g. `500%g`).
+    Yield { value: f64, unit: String },
+}
+
+/// Where templates referenced by `{% include %}`, `{% extends %}` and `{% import %}` come from.
+#[derive(Clone, Debug)]
+pub(crate) enum TemplateSource {
+    /// Templates are files under this directory.
+    Dir(PathBuf),
+    /// Templates are looked up by name in this map.
+    Map(BTreeMap<String, String>),
+}
+
 /// Builder for building a [`Config`].
 pub struct ConfigBuilder {
-    scale: f64,
-    datastore_path: Option<PathBuf>,
+    scaling: Scaling,
+    datastore_paths: Vec<PathBuf>,
     base_path: Option<PathBuf>,
     aisle_path: Option<PathBuf>,
+    store_aisle_paths: BTreeMap<String, PathBuf>,
     pantry_path: Option<PathBuf>,
+    templates: Option<TemplateSource>,
+    strict: bool,
+    practical_rounding: bool,
+    rounding_rules: BTreeMap<String, RoundingRule>,
 }
 
 impl Default for ConfigBuilder {
     /// Return a default [`ConfigBuilder`] with a scale of 1, no datastore path, aisle path, pantry path, and base path set to the current working directory.
     fn default() -> Self {
         Self {
-            scale: 1.0,
-            datastore_path: None,
+            scaling: Scaling::Factor(1.0),
+            datastore_paths: Vec::new(),
             base_path: std::env::current_dir().ok(),
             aisle_path: None,
+            store_aisle_paths: BTreeMap::new(),
             pantry_path: None,
+            templates: None,
+            strict: false,
+            practical_rounding: false,
+            rounding_rules: BTreeMap::new(),
         }
     }
 }
 
 impl ConfigBuilder {
     /// Set the scale property. This is used in recipe scaling and is itself passed to the template.
+    ///
+    /// Replaces any target set with [`target_servings()`][`Self::target_servings`] or
+    /// [`target_yield()`][`Self::target_yield`].
     pub fn scale(&mut self, scale: f64) -> &mut Self {
-        self.scale = scale;
+        self.scaling = Scaling::Factor(scale);
+        self
+    }
+
+    /// Scale the recipe to `servings` servings, based on its `servings` metadata.
+    ///
+    /// The resulting factor is passed to the template as `scale`. Rendering fails with
+    /// [`ScaleError`][`crate::Error::ScaleError`] if the recipe has no numeric servings.
+    ///
+    /// Replaces any scale or yield target set before.
+    pub fn target_servings(&mut self, servings: u32) -> &mut Self {
+        self.scaling = Scaling::Servings(servings);
+        self
+    }
+
+    /// Scale the recipe to yield `value` `unit`, based on its `yield` metadata (e.g. `500%g`).
+    ///
+    /// The resulting factor is passed to the template as `scale`. Rendering fails with
+    /// [`ScaleError`][`crate::Error::ScaleError`] if the recipe has no valid yield or its unit
+    /// is not `unit`.
+    ///
+    /// Replaces any scale or servings target set before.
+    pub fn target_yield<U: Into<String>>(&mut self, value: f64, unit: U) -> &mut Self {
+        self.scaling = Scaling::Yield {
+            value,
+            unit: unit.into(),
+        };
         self
     }
 
     /// Set a path to a [datastore][`yaml_datastore`].
+    ///
+    /// Replaces any datastores set with [`datastore_paths()`][`Self::datastore_paths`].
     pub fn datastore_path<P: Into<PathBuf>>(&mut self, datstore_path: P) -> &mut Self {
-        self.datastore_path = Some(datstore_path.into());
+        self.datastore_paths = vec![datstore_path.into()];
+        self
+    }
+
+    /// Set an ordered list of [datastore][`yaml_datastore`] roots, e.g. per-household
+    /// overrides before a shared datastore.
+    ///
+    /// A key is looked up in each datastore in turn and resolves from the first one that has
+    /// it; `db_source(key)` tells which one that was. Values aren't merged across datastores,
+    /// so `db('eggs.shopping')` returns the whole `shopping.yml` of the first datastore that
+    /// has one.
+    ///
+    /// Replaces any datastore set with [`datastore_path()`][`Self::datastore_path`].
+    pub fn datastore_paths<I, P>(&mut self, datastore_paths: I) -> &mut Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.datastore_paths = datastore_paths.into_iter().map(Into::into).collect();
         self
     }
 
@@ -124,20 +214,134 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set a path to the aisle configuration file of a named store.
+    ///
+    /// Templates pick a store with `aisled(ingredients, store="costco")` and can list the
+    /// configured stores with the `stores` variable. Without a store, `aisled` uses the file
+    /// set with [`aisle_path()`][`Self::aisle_path`]. Setting the same store again replaces
+    /// its path.
+    ///
+    /// ```
+    /// use cooklang_reports::config::Config;
+    /// let config = Config::builder()
+    ///     .aisle_path("aisle.conf")
+    ///     .aisle_config("costco", "costco.conf")
+    ///     .build();
+    /// ```
+    pub fn aisle_config<S: Into<String>, P: Into<PathBuf>>(
+        &mut self,
+        store_name: S,
+        aisle_path: P,
+    ) -> &mut Self {
+        self.store_aisle_paths
+            .insert(store_name.into(), aisle_path.into());
+        self
+    }
+
     /// Set a path to a pantry configuration file for filtering out pantry items.
     pub fn pantry_path<P: Into<PathBuf>>(&mut self, pantry_path: P) -> &mut Self {
         self.pantry_path = Some(pantry_path.into());
         self
     }
 
+    /// Set a directory to load templates from.
+    ///
+    /// Templates can then `{% include %}`, `{% extends %}` or `{% import %}` other templates by
+    /// their path relative to this directory, e.g. `{% include "partials/header.md.jinja" %}`.
+    /// Names that would resolve outside the directory are rejected.
+    ///
+    /// Replaces any templates set with [`templates()`][`Self::templates`].
+    pub fn template_dir<P: Into<PathBuf>>(&mut self, template_dir: P) -> &mut Self {
+        self.templates = Some(TemplateSource::Dir(template_dir.into()));
+        self
+    }
+
+    /// Set in-memory templates, by name, that can be included, extended or imported.
+    ///
+    /// This is an alternative to [`template_dir()`][`Self::template_dir`] for templates that
+    /// don't live on disk, and replaces it if both are set.
+    ///
+    /// ```
+    /// use cooklang_reports::config::Config;
+    /// let config = Config::builder()
+    ///     .templates([("header", "# {{ metadata.title }}")])
+    ///     .build();
+    /// ```
+    pub fn templates<I, K, V>(&mut self, templates: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.templates = Some(TemplateSource::Map(
+            templates
+                .into_iter()
+                .map(|(name, source)| (name.into(), source.into()))
+                .collect(),
+        ));
+        self
+    }
+
+    /// Turn problems that are otherwise warnings into errors, e.g. to validate reports in CI.
+    ///
+    /// In strict mode:
+    /// * [`Renderer::new()`][`crate::Renderer::new`] fails with
+    ///   [`ConfigError`][`crate::Error::ConfigError`] if the aisle or pantry files can't be
+    ///   read or have problems.
+    /// * `db()` fails for a missing key, naming the key path and the datastore.
+    /// * `aisled` and the pantry functions fail when no aisle or pantry file is configured,
+    ///   instead of falling back.
+    /// * Undefined variables and attributes are errors, using minijinja's
+    ///   [`Strict`][`minijinja::UndefinedBehavior::Strict`] undefined behavior.
+    ///
+    /// Errors raised while rendering are [`TemplateError`][`crate::Error::TemplateError`]s that
+    /// point at the template location.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Round every ingredient's quantity to a practical size after scaling, like whole eggs,
+    /// the nearest 1/4 tsp or 5 g, and 16 tbsp as 1 cup.
+    ///
+    /// See [`rounding`][`crate::rounding`] for the rules. Without this, quantities can still
+    /// be rounded one at a time in a template with the `practical` filter.
+    pub fn practical_rounding(&mut self, practical_rounding: bool) -> &mut Self {
+        self.practical_rounding = practical_rounding;
+        self
+    }
+
+    /// Set how quantities in `unit` are rounded by practical rounding and the `practical`
+    /// filter, replacing the default rule for it. Use `""` for quantities without a unit.
+    ///
+    /// ```
+    /// use cooklang_reports::config::Config;
+    /// use cooklang_reports::rounding::RoundingRule;
+    ///
+    /// let config = Config::builder()
+    ///     .practical_rounding(true)
+    ///     .rounding_rule("", RoundingRule::nearest(0.5))
+    ///     .rounding_rule("pinch", RoundingRule::nearest(1.0))
+    ///     .build();
+    /// ```
+    pub fn rounding_rule<U: Into<String>>(&mut self, unit: U, rule: RoundingRule) -> &mut Self {
+        self.rounding_rules.insert(unit.into(), rule);
+        self
+    }
+
     /// Return a new [`Config`] based on the builder's properties.
     pub fn build(&mut self) -> Config {
         Config {
-            scale: self.scale,
-            datastore_path: self.datastore_path.clone(),
+            scaling: self.scaling.clone(),
+            datastore_paths: self.datastore_paths.clone(),
             base_path: self.base_path.clone(),
             aisle_path: self.aisle_path.clone(),
+            store_aisle_paths: self.store_aisle_paths.clone(),
             pantry_path: self.pantry_path.clone(),
+            templates: self.templates.clone(),
+            strict: self.strict,
+            practical_rounding: self.practical_rounding,
+            rounding_rules: self.rounding_rules.clone(),
             extensions: Vec::new(),
             extra_context: BTreeMap::new(),
         }
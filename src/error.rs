@@ -3,15 +3,48 @@
 use thiserror::Error;
 
 /// Error type for this crate.
+///
+/// New variants may be added in minor releases, so matches on it need a wildcard arm.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// An error occurred when parsing the recipe.
     #[error("error parsing recipe")]
     RecipeParseError(#[from] cooklang::error::SourceReport),
 
+    /// An error occurred when parsing one recipe of a collection.
+    #[error("error parsing recipe '{name}'")]
+    CollectionRecipeParseError {
+        /// The name the recipe was given in the collection.
+        name: String,
+        /// The parser's report.
+        #[source]
+        report: cooklang::error::SourceReport,
+    },
+
+    /// The recipe could not be scaled to the configured target servings or yield.
+    #[error("error scaling recipe")]
+    ScaleError(#[from] cooklang::scale::ScaleError),
+
+    /// One recipe of a collection could not be scaled to the configured target servings or
+    /// yield.
+    #[error("error scaling recipe '{name}'")]
+    CollectionScaleError {
+        /// The name the recipe was given in the collection.
+        name: String,
+        /// Why scaling failed.
+        #[source]
+        source: cooklang::scale::ScaleError,
+    },
+
     /// An error occurred when generating a report from a template.
     #[error("template error")]
     TemplateError(#[from] minijinja::Error),
+
+    /// In strict mode, a problem with the aisle or pantry files that is otherwise only a
+    /// warning.
+    #[error("configuration error: {0}")]
+    ConfigError(crate::warning::Warning),
 }
 
 impl Error {
@@ -0,0 +1,85 @@
+use crate::model::{Quantity, quantity_from_value};
+use minijinja::{Error, ErrorKind::InvalidOperation, Value};
+
+/// Multiply a quantity by a factor, keeping its unit.
+///
+/// minijinja has no operator overloading for objects, so `quantity * 2` is an error. This
+/// filter does the same as `quantity.mul(2)`, and can be chained with other filters.
+///
+/// # Template Usage
+/// ```jinja
+/// {{ ingredient.quantity | mul(2) }}
+/// {{ ingredient.quantity | mul(2) | kitchen }}
+/// ```
+///
+/// # Errors
+///
+/// Returns an `InvalidOperation` error if the value is not a quantity or has a text value.
+pub fn mul_filter(value: &Value, factor: f64) -> Result<Value, Error> {
+    Ok(to_quantity(value)?.try_mul(factor)?.into())
+}
+
+/// Add two quantities, converting `other` to the unit of the first one.
+///
+/// minijinja has no operator overloading for objects, so `q1 + q2` is an error. This
+/// filter does the same as `q1.add(q2)`, and can be chained with other filters.
+///
+/// # Template Usage
+/// ```jinja
+/// {{ flour.quantity | add(more_flour.quantity) }}
+/// {{ flour.quantity | mul(2) | add(more_flour.quantity) | convert("kg") }}
+/// ```
+///
+/// # Errors
+///
+/// Returns an `InvalidOperation` error if either value is not a quantity, or if they can't
+/// be added, like text values or incompatible units.
+pub fn add_filter(value: &Value, other: &Value) -> Result<Value, Error> {
+    let other = quantity_from_value(other).map_err(|e| Error::new(InvalidOperation, e))?;
+    Ok(to_quantity(value)?.try_add(&other)?.into())
+}
+
+fn to_quantity(value: &Value) -> Result<Quantity, Error> {
+    quantity_from_value(value)
+        .map(Quantity::from)
+        .map_err(|e| Error::new(InvalidOperation, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::get_parser;
+    use minijinja::{Environment, context};
+    use test_case::test_case;
+
+    #[test_case("{{ a | mul(2) }}", Ok("1000 g"); "multiply")]
+    #[test_case("{{ a | mul(2) | add(b) }}", Ok("1250 g"); "chained")]
+    #[test_case("{{ b | add(a) }}", Ok("0.75 kg"); "converts to first unit")]
+    #[test_case("{{ a * 2 }}", Err("unsupported types"); "operator")]
+    #[test_case("{{ a | add(c) }}", Err("cannot add"); "incompatible units")]
+    #[test_case("{{ 2 | mul(2) }}", Err("is not a quantity"); "not a quantity")]
+    fn quantity_arithmetic(template: &str, expected: Result<&str, &str>) {
+        let (recipe, _warnings) = get_parser()
+            .parse("Mix @flour{500%g}, @sugar{0.25%kg} and @milk{1%l}.")
+            .into_result()
+            .unwrap();
+        let quantities: Vec<_> = recipe
+            .ingredients
+            .iter()
+            .map(|ingredient| Value::from(Quantity::from(ingredient.quantity.clone().unwrap())))
+            .collect();
+        let mut env = Environment::new();
+        env.add_filter("mul", mul_filter);
+        env.add_filter("add", add_filter);
+        let context = context! { a => quantities[0], b => quantities[1], c => quantities[2] };
+
+        let result = env.render_str(template, context);
+        match expected {
+            Ok(expected) => assert_eq!(expected, result.unwrap()),
+            Err(expected) => {
+                let err = result.unwrap_err().to_string();
+                assert!(err.contains(expected), "{err}");
+            }
+        }
+    }
+}
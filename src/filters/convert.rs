@@ -0,0 +1,176 @@
+use crate::model::{GroupedQuantity, IngredientListItem, Quantity, quantity_from_value};
+use crate::parser::get_unit_converter;
+use cooklang::convert::{ConvertTo, System};
+use cooklang::quantity::{
+    GroupedQuantity as CooklangGroupedQuantity, Quantity as CooklangQuantity,
+};
+use minijinja::value::Kwargs;
+use minijinja::{Error, ErrorKind::InvalidOperation, Value};
+
+/// Convert a quantity to another unit.
+///
+/// Works on a quantity (e.g. `ingredient.quantity`), on an item returned by
+/// `get_ingredient_list` or on its `quantities`. Items and their quantities are converted
+/// to a list of quantities, merged where possible.
+///
+/// # Template Usage
+/// ```jinja
+/// {{ ingredient.quantity | convert("g") }}
+/// {{ convert(ingredient.quantity, to="cup") }}
+/// {% for item in get_ingredient_list(ingredients) %}
+/// - {{ item.name }}: {{ item | convert(to="g") }}
+/// {% endfor %}
+/// ```
+///
+/// # Errors
+///
+/// Returns an `InvalidOperation` error if no target unit is given, or if the quantity has
+/// no unit, has a text value or a unit that can't be converted to the target.
+#[allow(clippy::needless_pass_by_value)]
+pub fn convert_filter(value: &Value, to: Option<&str>, kwargs: Kwargs) -> Result<Value, Error> {
+    let to = match (to, kwargs.get::<Option<&str>>("to")?) {
+        (Some(to), None) | (None, Some(to)) => to,
+        (Some(_), Some(_)) => {
+            return Err(Error::new(
+                InvalidOperation,
+                "convert takes the target unit either as an argument or as `to`, not both",
+            ));
+        }
+        (None, None) => {
+            return Err(Error::new(
+                InvalidOperation,
+                "convert needs a target unit, e.g. convert(quantity, to=\"g\")",
+            ));
+        }
+    };
+    kwargs.assert_all_used()?;
+
+    convert_value(value, ConvertTo::from(to), &format!("'{to}'"))
+}
+
+/// Convert a quantity to the best fitting unit of a unit system, `"metric"` or `"imperial"`.
+///
+/// Accepts the same values as [`convert_filter`].
+///
+/// # Template Usage
+/// ```jinja
+/// {{ ingredient.quantity | to_system("imperial") }}
+/// ```
+///
+/// # Errors
+///
+/// Returns an `InvalidOperation` error for an unknown unit system, or if the quantity can't
+/// be converted.
+pub fn to_system_filter(value: &Value, system: &str) -> Result<Value, Error> {
+    let to: System = system.parse().map_err(|_| {
+        Error::new(
+            InvalidOperation,
+            format!("unknown unit system '{system}', expected 'metric' or 'imperial'"),
+        )
+    })?;
+
+    convert_value(value, ConvertTo::from(to), system)
+}
+
+/// Convert a quantity, an ingredient list item or grouped quantities.
+fn convert_value(value: &Value, to: ConvertTo<'_>, target: &str) -> Result<Value, Error> {
+    if let Some(item) = value.downcast_object_ref::<IngredientListItem>() {
+        return convert_grouped(&item.quantities, to, target).map(Value::from);
+    }
+    if let Some(grouped) = value.downcast_object_ref::<GroupedQuantity>() {
+        return convert_grouped(grouped, to, target).map(Value::from);
+    }
+
+    let quantity = quantity_from_value(value).map_err(|e| Error::new(InvalidOperation, e))?;
+    convert_quantity(quantity, to, target).map(|q| Value::from(Quantity::from(q)))
+}
+
+fn convert_grouped(
+    grouped: &GroupedQuantity,
+    to: ConvertTo<'_>,
+    target: &str,
+) -> Result<GroupedQuantity, Error> {
+    let mut converted = CooklangGroupedQuantity::empty();
+    for quantity in grouped.to_cooklang() {
+        converted.add(
+            &convert_quantity(quantity, to, target)?,
+            get_unit_converter(),
+        );
+    }
+    Ok(GroupedQuantity::from(converted))
+}
+
+/// Convert a single quantity with the bundled units converter.
+pub(crate) fn convert_quantity(
+    mut quantity: CooklangQuantity,
+    to: ConvertTo<'_>,
+    target: &str,
+) -> Result<CooklangQuantity, Error> {
+    let original = quantity.to_string();
+    quantity.convert(to, get_unit_converter()).map_err(|e| {
+        Error::new(
+            InvalidOperation,
+            format!("cannot convert '{original}' to {target}: {e}"),
+        )
+    })?;
+    Ok(quantity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Ingredient, IngredientList};
+    use crate::parser::{get_converter, get_parser};
+    use minijinja::{Environment, context};
+    use test_case::test_case;
+
+    fn render(recipe: &str, template: &str) -> Result<String, Error> {
+        let (recipe, _warnings) = get_parser().parse(recipe).into_result().unwrap();
+        let mut env = Environment::new();
+        env.add_template("test", template)?;
+        env.add_filter("convert", convert_filter);
+        env.add_filter("to_system", to_system_filter);
+        env.add_function("convert", convert_filter);
+
+        let ingredients: Vec<Value> = recipe
+            .ingredients
+            .iter()
+            .cloned()
+            .map(|i| Value::from(Ingredient::from(i)))
+            .collect();
+        let mut list = cooklang::ingredient_list::IngredientList::new();
+        list.add_recipe(&recipe, get_converter(), false);
+
+        env.get_template("test")?.render(context! {
+            ingredients,
+            list => Value::from(IngredientList::from_cooklang(list)),
+        })
+    }
+
+    #[test_case("@flour{1%kg}", "{{ ingredients[0].quantity | convert('g') }}", "1000 g"; "metric positional")]
+    #[test_case("@flour{1%kg}", "{{ convert(ingredients[0].quantity, to='g') }}", "1000 g"; "function with keyword")]
+    #[test_case("@butter{1%lb}", "{{ ingredients[0].quantity | convert(to='g') }}", "453.592 g"; "imperial to metric")]
+    #[test_case("@milk{1%l}", "{{ ingredients[0].quantity | convert(to='ml') }}", "1000 ml"; "volume")]
+    #[test_case("@flour{1%kg}", "{{ {'value': '1-2', 'unit': 'kg'} | convert(to='g') }}", "1000-2000 g"; "range")]
+    #[test_case("@flour{1%kg}", "{{ (ingredients[0].quantity | convert(to='g')).unit }}", "g"; "result is a quantity")]
+    #[test_case("@butter{1%lb}", "{{ ingredients[0].quantity | to_system('metric') }}", "453.592 g"; "to metric")]
+    #[test_case("@flour{1000%g}", "{{ ingredients[0].quantity | to_system('metric') }}", "1 kg"; "to best metric unit")]
+    #[test_case("@flour{500%g} @flour{1%kg}", "{{ list[0] | convert(to='g') }}", "1500 g"; "ingredient list item")]
+    #[test_case("@flour{1%kg}", "{{ list[0].quantities | convert(to='g') }}", "1000 g"; "grouped quantities")]
+    #[test_case("@flour{1%kg}", "{{ list[0].quantities[0] | convert(to='g') }}", "1000 g"; "single listed quantity")]
+    fn converts(recipe: &str, template: &str, expected: &str) {
+        assert_eq!(expected, render(recipe, template).unwrap());
+    }
+
+    #[test_case("@flour{1%kg}", "{{ ingredients[0].quantity | convert(to='ml') }}", "cannot convert '1 kg' to 'ml'"; "incompatible units")]
+    #[test_case("@eggs{2}", "{{ ingredients[0].quantity | convert(to='g') }}", "cannot convert '2' to 'g'"; "no unit")]
+    #[test_case("@salt{a pinch%g}", "{{ ingredients[0].quantity | convert(to='kg') }}", "cannot convert"; "text value")]
+    #[test_case("@flour{1%kg}", "{{ ingredients[0].quantity | convert(to='parsec') }}", "cannot convert '1 kg' to 'parsec'"; "unknown unit")]
+    #[test_case("@flour{1%kg}", "{{ ingredients[0].quantity | convert }}", "needs a target unit"; "missing target")]
+    #[test_case("@flour{1%kg}", "{{ ingredients[0].quantity | to_system('martian') }}", "unknown unit system 'martian'"; "unknown system")]
+    fn invalid_conversion(recipe: &str, template: &str, message: &str) {
+        let err = render(recipe, template).unwrap_err();
+        assert_eq!(err.kind(), InvalidOperation);
+        assert!(err.to_string().contains(message), "{err}");
+    }
+}
@@ -0,0 +1,276 @@
+use crate::model::{GroupedQuantity, IngredientListItem, parse_text_value, quantity_from_value};
+use crate::parser::get_unit_converter;
+use cooklang::convert::System;
+use cooklang::quantity::{Quantity as CooklangQuantity, Value as QuantityValue};
+use minijinja::value::Kwargs;
+use minijinja::{Error, ErrorKind::InvalidOperation, Value};
+
+/// Denominators found on measuring cups and spoons, tried from the simplest.
+const DENOMINATORS: [u32; 8] = [1, 2, 3, 4, 8, 16, 32, 64];
+
+/// Unicode vulgar fractions, by numerator and denominator.
+const VULGAR_FRACTIONS: [(u32, u32, char); 9] = [
+    (1, 2, '½'),
+    (1, 3, '⅓'),
+    (2, 3, '⅔'),
+    (1, 4, '¼'),
+    (3, 4, '¾'),
+    (1, 8, '⅛'),
+    (3, 8, '⅜'),
+    (5, 8, '⅝'),
+    (7, 8, '⅞'),
+];
+
+/// Digits for numerators and denominators without a vulgar fraction glyph.
+const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+const SUBSCRIPTS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+
+/// Options of the `fraction` filter.
+struct FractionStyle {
+    max_denominator: u32,
+    tolerance: f64,
+    unicode: bool,
+}
+
+/// Format a quantity with kitchen-friendly fractions, e.g. `0.375 cup` as `3/8 cup`.
+///
+/// Numbers are shown as a whole number and a fraction whose denominator is one of 2, 3, 4,
+/// 8, 16, 32 or 64, the simplest within `tolerance`. Numbers no fraction is close enough to,
+/// and quantities in metric units like `g` or `ml`, keep their decimals. Text values are
+/// shown as they are.
+///
+/// Works on a quantity, a plain number, an item returned by `get_ingredient_list` or its
+/// `quantities`. Also available as `kitchen`.
+///
+/// # Arguments
+/// * `max_denominator` - Optional keyword argument, the largest denominator to use. Defaults
+///   to 8.
+/// * `tolerance` - Optional keyword argument, the largest error allowed, relative to the
+///   number. Defaults to 0.05.
+/// * `unicode` - Optional keyword argument, `true` to use glyphs like `⅜` instead of `3/8`.
+///   Defaults to `false`.
+///
+/// # Template Usage
+/// ```jinja
+/// {{ ingredient.quantity | fraction }}
+/// {{ ingredient.quantity | kitchen(unicode=true) }}
+/// {{ ingredient.quantity | fraction(max_denominator=4, tolerance=0.1) }}
+/// ```
+///
+/// # Errors
+///
+/// Returns an `InvalidOperation` error if the value is not a quantity or a number, or if
+/// `max_denominator` is less than 1 or `tolerance` is not between 0 and 1.
+#[allow(clippy::needless_pass_by_value)]
+pub fn fraction_filter(value: &Value, kwargs: Kwargs) -> Result<String, Error> {
+    let style = FractionStyle {
+        max_denominator: kwargs.get::<Option<u32>>("max_denominator")?.unwrap_or(8),
+        tolerance: kwargs.get::<Option<f64>>("tolerance")?.unwrap_or(0.05),
+        unicode: kwargs.get::<Option<bool>>("unicode")?.unwrap_or(false),
+    };
+    kwargs.assert_all_used()?;
+    if style.max_denominator < 1 {
+        return Err(Error::new(
+            InvalidOperation,
+            "max_denominator must be at least 1",
+        ));
+    }
+    if !(0.0..1.0).contains(&style.tolerance) {
+        return Err(Error::new(
+            InvalidOperation,
+            "tolerance must be between 0 and 1",
+        ));
+    }
+
+    if let Some(item) = value.downcast_object_ref::<IngredientListItem>() {
+        return Ok(format_grouped(&item.quantities, &style));
+    }
+    if let Some(grouped) = value.downcast_object_ref::<GroupedQuantity>() {
+        return Ok(format_grouped(grouped, &style));
+    }
+    if let Ok(number) = f64::try_from(value.clone()) {
+        return Ok(format_number(number, &style));
+    }
+
+    let quantity = quantity_from_value(value).map_err(|e| Error::new(InvalidOperation, e))?;
+    Ok(format_quantity(&parse_text_value(&quantity), &style))
+}
+
+fn format_grouped(grouped: &GroupedQuantity, style: &FractionStyle) -> String {
+    grouped
+        .to_cooklang()
+        .iter()
+        .map(|quantity| format_quantity(&parse_text_value(quantity), style))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_quantity(quantity: &CooklangQuantity, style: &FractionStyle) -> String {
+    let is_metric = quantity.unit().is_some_and(|unit| {
+        get_unit_converter()
+            .find_unit(unit)
+            .is_some_and(|unit| unit.system == Some(System::Metric))
+    });
+    let value = match quantity.value() {
+        _ if is_metric => quantity.value().to_string(),
+        QuantityValue::Number(n) => format_number(n.value(), style),
+        QuantityValue::Range { start, end } => format!(
+            "{}-{}",
+            format_number(start.value(), style),
+            format_number(end.value(), style)
+        ),
+        QuantityValue::Text(text) => text.clone(),
+    };
+    match quantity.unit() {
+        Some(unit) => format!("{value} {unit}"),
+        None => value,
+    }
+}
+
+/// Format a number as a whole number and a fraction, or with decimals if no fraction is
+/// within the tolerance.
+fn format_number(number: f64, style: &FractionStyle) -> String {
+    let Some((whole, num, den)) = approximate(number, style) else {
+        return QuantityValue::from(number).to_string();
+    };
+    if num == 0 {
+        return whole.to_string();
+    }
+    let fraction = if style.unicode {
+        unicode_fraction(num, den)
+    } else {
+        format!("{num}/{den}")
+    };
+    match (whole, style.unicode) {
+        (0, _) => fraction,
+        (whole, true) => format!("{whole}{fraction}"),
+        (whole, false) => format!("{whole} {fraction}"),
+    }
+}
+
+/// Find the simplest whole number and fraction within the tolerance of `number`.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn approximate(number: f64, style: &FractionStyle) -> Option<(u64, u32, u32)> {
+    if !number.is_finite() || number <= 0.0 {
+        return None;
+    }
+    let whole = number.trunc();
+    let fraction = number - whole;
+    DENOMINATORS
+        .iter()
+        .filter(|&&den| den <= style.max_denominator)
+        .find_map(|&den| {
+            let num = (fraction * f64::from(den)).round();
+            let approximation = whole + num / f64::from(den);
+            if (approximation - number).abs() > style.tolerance * number {
+                return None;
+            }
+            // A fraction that rounds up to a whole number carries over
+            let whole = whole as u64 + u64::from(num as u32 == den);
+            Some((whole, (num as u32) % den, den))
+        })
+        .map(|(whole, num, den)| {
+            let divisor = gcd(num, den);
+            (whole, num / divisor, den / divisor)
+        })
+}
+
+/// Whether a number has a simple fractional part, like `0.25` or `2 2/3`, with a denominator
+/// of at most 8.
+pub(crate) fn is_simple_fraction(number: f64) -> bool {
+    let style = FractionStyle {
+        max_denominator: 8,
+        tolerance: 1e-6,
+        unicode: false,
+    };
+    approximate(number, &style).is_some_and(|(_, num, _)| num != 0)
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a.max(1) } else { gcd(b, a % b) }
+}
+
+/// A fraction as a single glyph where Unicode has one, otherwise with superscript and
+/// subscript digits, e.g. `⁵⁄₁₆`.
+fn unicode_fraction(num: u32, den: u32) -> String {
+    if let Some((_, _, glyph)) = VULGAR_FRACTIONS
+        .iter()
+        .find(|(n, d, _)| *n == num && *d == den)
+    {
+        return glyph.to_string();
+    }
+    let digits = |n: u32, glyphs: &[char; 10]| -> String {
+        n.to_string()
+            .chars()
+            .filter_map(|c| c.to_digit(10).map(|d| glyphs[d as usize]))
+            .collect()
+    };
+    format!(
+        "{}⁄{}",
+        digits(num, &SUPERSCRIPTS),
+        digits(den, &SUBSCRIPTS)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Ingredient, IngredientList};
+    use crate::parser::{get_converter, get_parser};
+    use minijinja::{Environment, context};
+    use test_case::test_case;
+
+    fn render(recipe: &str, template: &str) -> Result<String, Error> {
+        let (recipe, _warnings) = get_parser().parse(recipe).into_result().unwrap();
+        let mut env = Environment::new();
+        env.add_template("test", template)?;
+        env.add_filter("fraction", fraction_filter);
+        env.add_filter("kitchen", fraction_filter);
+
+        let ingredients: Vec<Value> = recipe
+            .ingredients
+            .iter()
+            .cloned()
+            .map(|i| Value::from(Ingredient::from(i)))
+            .collect();
+        let mut list = cooklang::ingredient_list::IngredientList::new();
+        list.add_recipe(&recipe, get_converter(), false);
+
+        env.get_template("test")?.render(context! {
+            ingredients,
+            list => Value::from(IngredientList::from_cooklang(list)),
+        })
+    }
+
+    #[test_case("@sugar{0.375%cup}", "{{ ingredients[0].quantity | fraction }}", "3/8 cup"; "fraction")]
+    #[test_case("@sugar{0.375%cup}", "{{ ingredients[0].quantity | kitchen }}", "3/8 cup"; "kitchen alias")]
+    #[test_case("@sugar{0.375%cup}", "{{ ingredients[0].quantity | fraction(unicode=true) }}", "⅜ cup"; "unicode")]
+    #[test_case("@eggs{2.6666}", "{{ ingredients[0].quantity | fraction }}", "2 2/3"; "mixed number")]
+    #[test_case("@eggs{2.6666}", "{{ ingredients[0].quantity | fraction(unicode=true) }}", "2⅔"; "unicode mixed number")]
+    #[test_case("@salt{0.3125%tsp}", "{{ ingredients[0].quantity | fraction(max_denominator=16) }}", "5/16 tsp"; "larger denominator")]
+    #[test_case("@salt{0.3125%tsp}", "{{ ingredients[0].quantity | fraction(max_denominator=16, unicode=true) }}", "⁵⁄₁₆ tsp"; "unicode without glyph")]
+    #[test_case("@salt{0.3125%tsp}", "{{ ingredients[0].quantity | fraction }}", "0.313 tsp"; "outside tolerance")]
+    #[test_case("@salt{0.3125%tsp}", "{{ ingredients[0].quantity | fraction(tolerance=0.25) }}", "1/3 tsp"; "wider tolerance")]
+    #[test_case("@milk{1.99%cup}", "{{ ingredients[0].quantity | fraction }}", "2 cup"; "rounds to whole number")]
+    #[test_case("@flour{0.375%kg}", "{{ ingredients[0].quantity | fraction }}", "0.375 kg"; "metric keeps decimals")]
+    #[test_case("@eggs{1-2}", "{{ ingredients[0].quantity | fraction }}", "1-2"; "range")]
+    #[test_case("@salt{a pinch}", "{{ ingredients[0].quantity | fraction }}", "a pinch"; "text")]
+    #[test_case("@sugar{0.25%cup} @sugar{0.5%cup}", "{{ list[0] | fraction }}", "3/4 cup"; "ingredient list item")]
+    #[test_case("@sugar{1}", "{{ 1.5 | fraction }}", "1 1/2"; "plain number")]
+    fn formats(recipe: &str, template: &str, expected: &str) {
+        assert_eq!(expected, render(recipe, template).unwrap());
+    }
+
+    #[test_case("{{ 0.5 | fraction(max_denominator=0) }}", "max_denominator"; "zero denominator")]
+    #[test_case("{{ 0.5 | fraction(tolerance=2) }}", "tolerance"; "tolerance too large")]
+    #[test_case("{{ 'half' | fraction }}", "is not a quantity"; "not a quantity")]
+    fn invalid_arguments(template: &str, message: &str) {
+        let err = render("@sugar{1}", template).unwrap_err();
+        assert_eq!(err.kind(), InvalidOperation);
+        assert!(err.to_string().contains(message), "{err}");
+    }
+}
@@ -1,7 +1,13 @@
+pub mod arithmetic;
+pub mod convert;
+pub mod fraction;
 pub mod numeric;
 pub mod price;
 pub mod string;
 
+pub use arithmetic::{add_filter, mul_filter};
+pub use convert::{convert_filter, to_system_filter};
+pub use fraction::fraction_filter;
 pub use numeric::numeric_filter;
 pub use price::format_price_filter;
 pub use string::{
@@ -1,7 +1,77 @@
-use crate::parser::get_converter;
-use cooklang::quantity::GroupedQuantity;
-use minijinja::{State, Value};
-use std::collections::BTreeMap;
+use crate::model::{GroupedQuantity, Ingredient, IngredientListItem};
+use crate::parser::get_unit_converter;
+use crate::warning::{Warning, WarningKind, emit, is_strict};
+use cooklang::quantity::GroupedQuantity as CooklangGroupedQuantity;
+use minijinja::value::{Kwargs, Object};
+use minijinja::{Error, ErrorKind::InvalidOperation, State, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// A parsed aisle configuration, shared by every call to `aisled` while rendering.
+///
+/// Unlike cooklang's `AisleConf`, it owns its names, so it can be parsed once when the
+/// renderer is built and passed to templates as an object.
+#[derive(Debug, Default)]
+pub(crate) struct AisleConfig {
+    /// Category names, each with the common name of its ingredients, in file order
+    categories: Vec<(String, Vec<String>)>,
+    /// Lowercase ingredient names and synonyms to their category and ingredient index
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl AisleConfig {
+    /// Parse an aisle configuration file, collecting its warnings.
+    ///
+    /// A file that can't be parsed gives an empty configuration, so every ingredient is
+    /// placed under "other", and a single warning.
+    pub(crate) fn parse(content: &str, warnings: &mut Vec<Warning>) -> Self {
+        let result = cooklang::aisle::parse_lenient(content);
+        warnings.extend(
+            result
+                .report()
+                .iter()
+                .map(|w| Warning::from_diag(WarningKind::Aisle, w)),
+        );
+
+        let Some(aisle_conf) = result.output() else {
+            warnings.push(Warning::new(
+                WarningKind::Aisle,
+                "Failed to parse aisle configuration. All ingredients will be placed under 'other' category.",
+            ));
+            return Self::default();
+        };
+
+        let mut config = Self::default();
+        for (category_index, category) in aisle_conf.categories.iter().enumerate() {
+            let mut ingredients = Vec::new();
+            for ingredient in &category.ingredients {
+                let Some(common_name) = ingredient.names.first() else {
+                    continue;
+                };
+                for name in &ingredient.names {
+                    // The first listing of a name wins, as in cooklang's categorize
+                    config
+                        .positions
+                        .entry(name.to_lowercase())
+                        .or_insert((category_index, ingredients.len()));
+                }
+                ingredients.push((*common_name).to_string());
+            }
+            config
+                .categories
+                .push((category.name.to_string(), ingredients));
+        }
+        config
+    }
+
+    /// Find an ingredient's category and index in the aisle file by its name.
+    fn position(&self, item: &Value) -> Option<(usize, usize)> {
+        let name = item.get_attr("name").ok()?;
+        self.positions.get(&name.as_str()?.to_lowercase()).copied()
+    }
+}
+
+impl Object for AisleConfig {}
 
 /// Group ingredients by aisle category using an aisle configuration file.
 ///
@@ -9,13 +79,28 @@ use std::collections::BTreeMap;
 /// as defined in the aisle configuration. Ingredients without a category are placed
 /// under "other".
 ///
+/// Categories, and the ingredients in each category, follow the order of the aisle file,
+/// so a shopping list can follow the route through the store. "other" always comes last,
+/// with its ingredients in the order they were given.
+///
+/// Ingredients are matched by name, ignoring case, against every synonym in the aisle file
+/// (`green onion|scallion`). Recipe ingredients and items from `get_ingredient_list` that
+/// match are renamed to the aisle file's name for them, the first synonym, and keep their
+/// other attributes. Items from `get_ingredient_list` that are synonyms of each other are
+/// also merged, with their quantities combined. Other values are returned as they were
+/// given.
+///
 /// # Arguments
-/// * `ingredients` - The list of ingredients to categorize
+/// * `ingredients` - The recipe's ingredients or the result of `get_ingredient_list`
+/// * `store` - Optional keyword argument with the name of a store configured with
+///   `ConfigBuilder::aisle_config`. Defaults to the aisle file set with `aisle_path`.
+/// * `sort` - Optional keyword argument, `"aisle"` (the default) for aisle file order or
+///   `"alphabetical"` to sort categories and ingredients by name
 ///
 /// # Returns
 /// A map where keys are aisle categories and values are lists of ingredients.
 /// If no aisle configuration is available, returns all ingredients under "other" category
-/// and logs a warning.
+/// and records a warning.
 ///
 /// # Template Usage
 /// ```jinja
@@ -25,105 +110,174 @@ use std::collections::BTreeMap;
 /// - {{ ingredient.name }}: {{ ingredient.quantities }}
 /// {% endfor %}
 /// {% endfor %}
+///
+/// {% for store in stores %}
+/// # {{ store }}
+/// {% for aisle, items in aisled(ingredients, store=store) | items %}...{% endfor %}
+/// {% endfor %}
 /// ```
-pub fn aisled(state: &State, ingredients: Value) -> Value {
-    // Try to get aisle content from state
-    let aisle_content = state
-        .lookup("aisle_content")
-        .and_then(|v| v.as_str().map(String::from));
-
-    let mut result = BTreeMap::new();
-
-    if let Some(content) = aisle_content {
-        // Parse the aisle configuration
-        let parse_result = cooklang::aisle::parse_lenient(&content);
-
-        if let Some(aisle_conf) = parse_result.output() {
-            // Build an IngredientList from the ingredients value
-            let mut ingredient_list = cooklang::ingredient_list::IngredientList::new();
-
-            // Extract ingredients from the minijinja Value
-            if let Ok(iter) = ingredients.try_iter() {
-                for item in iter {
-                    // Get ingredient name
-                    let name = item
-                        .get_attr("name")
-                        .ok()
-                        .and_then(|v| v.as_str().map(String::from))
-                        .unwrap_or_default();
-
-                    // For categorization, we don't need quantities
-                    // Just add the ingredient with empty quantity
-                    ingredient_list.add_ingredient(
-                        name,
-                        &GroupedQuantity::default(),
-                        get_converter(),
-                    );
-                }
-            }
+///
+/// # Errors
+///
+/// Returns an `InvalidOperation` error for an unknown `store` or `sort` order, or if
+/// `ingredients` is not a list. In strict mode, also if no aisle configuration is provided.
+#[allow(clippy::needless_pass_by_value)]
+pub fn aisled(state: &State, ingredients: Value, kwargs: Kwargs) -> Result<Value, Error> {
+    let alphabetical = match kwargs.get::<Option<&str>>("sort")? {
+        None | Some("aisle") => false,
+        Some("alphabetical") => true,
+        Some(other) => {
+            return Err(Error::new(
+                InvalidOperation,
+                format!("unknown sort order '{other}', expected 'aisle' or 'alphabetical'"),
+            ));
+        }
+    };
+    let store = kwargs.get::<Option<&str>>("store")?;
+    kwargs.assert_all_used()?;
 
-            // Categorize the ingredients
-            let categorized = ingredient_list.categorize(aisle_conf);
-
-            // Convert categorized ingredients back to template values
-            // Process categories
-            for (category, list) in categorized.categories {
-                let mut category_items = Vec::new();
-
-                // Find the original ingredient data from the input
-                for (ingredient_name, _) in list {
-                    if let Ok(iter) = ingredients.try_iter() {
-                        for item in iter {
-                            if let Ok(name) = item.get_attr("name")
-                                && name.as_str() == Some(&ingredient_name)
-                            {
-                                category_items.push(item);
-                                break;
-                            }
-                        }
-                    }
-                }
+    // Try to get the aisle configuration from state, for the store if one is named
+    let aisle_config = match store {
+        Some(store) => store_aisle_config(state, store)?,
+        None => state
+            .lookup("aisle_config")
+            .and_then(|v| v.downcast_object::<AisleConfig>()),
+    };
 
-                if !category_items.is_empty() {
-                    result.insert(category, Value::from(category_items));
-                }
-            }
+    let Some(aisle_config) = aisle_config else {
+        let (error, warning) = match store {
+            // The store was configured, but its file couldn't be read
+            Some(store) => (
+                format!("aisle configuration for store '{store}' could not be loaded"),
+                format!(
+                    "Aisle configuration for store '{store}' could not be loaded. All ingredients will be placed under 'other' category."
+                ),
+            ),
+            None => (
+                "no aisle configuration provided, use Config::builder().aisle_path(path)"
+                    .to_string(),
+                "No aisle configuration provided. All ingredients will be placed under 'other' category. To configure aisles, use Config::builder().aisle_path(path)"
+                    .to_string(),
+            ),
+        };
+        if is_strict(state) {
+            return Err(Error::new(InvalidOperation, error));
+        }
+        emit(state, Warning::new(WarningKind::Aisle, warning));
+        return Ok(Value::from_iter([("other", ingredients)]));
+    };
 
-            // Process "other" category
-            if !categorized.other.is_empty() {
-                let mut other_items = Vec::new();
-
-                for (ingredient_name, _) in categorized.other {
-                    if let Ok(iter) = ingredients.try_iter() {
-                        for item in iter {
-                            if let Ok(name) = item.get_attr("name")
-                                && name.as_str() == Some(&ingredient_name)
-                            {
-                                other_items.push(item);
-                                break;
-                            }
-                        }
-                    }
-                }
+    // Group by position in the aisle file: category index, then ingredient index
+    let mut categories: BTreeMap<usize, Vec<(usize, Value)>> = BTreeMap::new();
+    let mut other = Vec::new();
+    for item in ingredients.try_iter()? {
+        let Some((category, index)) = aisle_config.position(&item) else {
+            other.push(item);
+            continue;
+        };
+        let items = categories.entry(category).or_default();
+        let common_name = aisle_config.categories[category].1[index].clone();
 
-                if !other_items.is_empty() {
-                    result.insert("other".to_string(), Value::from(other_items));
-                }
+        let Some(list_item) = item.downcast_object_ref::<IngredientListItem>() else {
+            let item = match item.downcast_object_ref::<Ingredient>() {
+                Some(ingredient) => Value::from(ingredient.renamed(common_name)),
+                None => item,
+            };
+            items.push((index, item));
+            continue;
+        };
+        // Synonyms of an ingredient that is already listed are merged into it
+        let merged = items.iter_mut().find_map(|(i, existing)| {
+            if *i != index {
+                return None;
             }
-        } else {
-            // Failed to parse aisle configuration
-            eprintln!(
-                "Warning: Failed to parse aisle configuration. All ingredients will be placed under 'other' category."
-            );
-            result.insert("other".to_string(), ingredients);
+            let existing_item = existing.downcast_object_ref::<IngredientListItem>()?;
+            let quantities = merge_quantities(&existing_item.quantities, &list_item.quantities);
+            Some((existing, quantities))
+        });
+        match merged {
+            Some((existing, quantities)) => {
+                *existing = Value::from(IngredientListItem::new(common_name, quantities));
+            }
+            None => items.push((
+                index,
+                Value::from(IngredientListItem::new(
+                    common_name,
+                    list_item.quantities.clone(),
+                )),
+            )),
         }
-    } else {
-        // No aisle configuration provided
-        eprintln!(
-            "Warning: No aisle configuration provided. All ingredients will be placed under 'other' category. To configure aisles, use Config::builder().aisle_path(path)"
-        );
-        result.insert("other".to_string(), ingredients);
     }
 
-    Value::from_iter(result)
+    let mut result: Vec<(&str, Vec<Value>)> = categories
+        .into_iter()
+        .map(|(category, mut items)| {
+            items.sort_by_key(|(index, _)| *index);
+            let items = items.into_iter().map(|(_, item)| item).collect();
+            (aisle_config.categories[category].0.as_str(), items)
+        })
+        .collect();
+
+    if alphabetical {
+        result.sort_by_key(|(category, _)| category.to_lowercase());
+        for (_, items) in &mut result {
+            sort_by_name(items);
+        }
+        sort_by_name(&mut other);
+    }
+
+    if !other.is_empty() {
+        result.push(("other", other));
+    }
+
+    Ok(result
+        .into_iter()
+        .map(|(category, items)| (category, Value::from(items)))
+        .collect())
+}
+
+/// Look up the aisle configuration of a named store, `None` if its file couldn't be read.
+fn store_aisle_config(state: &State, store: &str) -> Result<Option<Arc<AisleConfig>>, Error> {
+    let stores: Vec<String> = state
+        .lookup("stores")
+        .and_then(|stores| stores.try_iter().ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|store| store.as_str().map(String::from))
+        .collect();
+    if !stores.iter().any(|name| name == store) {
+        let configured = if stores.is_empty() {
+            "none".to_string()
+        } else {
+            stores.join(", ")
+        };
+        return Err(Error::new(
+            InvalidOperation,
+            format!("unknown store '{store}', configured stores: {configured}"),
+        ));
+    }
+
+    Ok(state
+        .lookup("store_aisle_configs")
+        .and_then(|configs| configs.get_attr(store).ok())
+        .and_then(|config| config.downcast_object::<AisleConfig>()))
+}
+
+/// Combine the quantities of two ingredient list items.
+fn merge_quantities(a: &GroupedQuantity, b: &GroupedQuantity) -> GroupedQuantity {
+    let mut merged = CooklangGroupedQuantity::empty();
+    for quantity in a.to_cooklang().iter().chain(&b.to_cooklang()) {
+        merged.add(quantity, get_unit_converter());
+    }
+    GroupedQuantity::from(merged)
+}
+
+/// Sort ingredients by name, ignoring case.
+fn sort_by_name(items: &mut [Value]) {
+    items.sort_by_cached_key(|item| {
+        item.get_attr("name")
+            .ok()
+            .and_then(|name| name.as_str().map(str::to_lowercase))
+            .unwrap_or_default()
+    });
 }
@@ -0,0 +1,130 @@
+use super::datastore::{LayeredDatastore, datastore_from_state};
+use crate::filters::convert::convert_quantity;
+use crate::model::{ingredient_quantities, quantity_number};
+use crate::parser::get_unit_converter;
+use cooklang::convert::ConvertTo;
+use cooklang::quantity::Quantity;
+use minijinja::{Error, State, Value};
+use serde::{Deserialize, Serialize};
+
+/// A price from an ingredient's `shopping.yml`: `price` buys `quantity` `unit`s.
+#[derive(Debug, Deserialize)]
+struct ShoppingPrice {
+    price: f64,
+    #[serde(default = "default_price_quantity")]
+    quantity: f64,
+    unit: Option<String>,
+}
+
+fn default_price_quantity() -> f64 {
+    1.0
+}
+
+/// Costs for a list of ingredients, as returned to the template.
+#[derive(Debug, Default, Serialize)]
+struct Cost {
+    ingredients: Vec<PricedIngredient>,
+    total: f64,
+    unpriced: Vec<String>,
+}
+
+/// The cost of a single ingredient.
+#[derive(Debug, Serialize)]
+struct PricedIngredient {
+    name: String,
+    cost: f64,
+}
+
+/// Calculate the cost of a list of ingredients from prices in the datastore.
+///
+/// Each ingredient needs a `shopping.yml` in its datastore directory with a `price` for a
+/// `quantity` (defaults to 1) of a `unit`. Quantities are converted to the priced unit, so a
+/// recipe can ask for grams of something priced per kilogram. Without a `unit`, the price is
+/// per item and the quantity is used as a count. Ranges count as their midpoint.
+///
+/// ```yaml
+/// price: 1.50
+/// quantity: 1
+/// unit: kg
+/// ```
+///
+/// # Arguments
+/// * `ingredients` - The recipe's ingredients or the result of `get_ingredient_list`
+///
+/// # Returns
+/// A map with:
+/// * `ingredients` - the `name` and `cost` of each priced ingredient
+/// * `total` - the sum of those costs
+/// * `unpriced` - names of ingredients with no price, no quantity, or a quantity that could
+///   not be converted to the priced unit
+///
+/// # Template Usage
+/// ```jinja
+/// {% set costs = cost(ingredients) %}
+/// {% for item in costs.ingredients %}
+/// * {{ item.name }}: ${{ item.cost | format_price }}
+/// {% endfor %}
+/// Total: ${{ costs.total | format_price }}
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if no datastore is configured.
+#[allow(clippy::needless_pass_by_value)]
+pub fn cost(state: &State, ingredients: Value) -> Result<Value, Error> {
+    let datastore = datastore_from_state(state)?;
+    let mut result = Cost::default();
+
+    for ingredient in ingredients.try_iter()? {
+        let Some(name) = ingredient
+            .get_attr("name")
+            .ok()
+            .and_then(|n| n.as_str().map(String::from))
+        else {
+            continue;
+        };
+
+        match ingredient_cost(&datastore, &name, &ingredient_quantities(&ingredient)) {
+            Some(cost) => {
+                result.total += cost;
+                result.ingredients.push(PricedIngredient { name, cost });
+            }
+            None => result.unpriced.push(name),
+        }
+    }
+
+    Ok(Value::from_serialize(&result))
+}
+
+/// Price one ingredient, or `None` if its price or quantity is missing or incompatible.
+fn ingredient_cost(
+    datastore: &LayeredDatastore,
+    name: &str,
+    quantities: &[Quantity],
+) -> Option<f64> {
+    let price: ShoppingPrice = datastore.get(&format!("{name}.shopping")).ok()?;
+    if quantities.is_empty() || price.quantity <= 0.0 {
+        return None;
+    }
+
+    quantities
+        .iter()
+        .map(|q| priced_amount(q, price.unit.as_deref()))
+        .sum::<Option<f64>>()
+        .map(|amount| price.price * amount / price.quantity)
+}
+
+/// How many priced units a quantity is.
+fn priced_amount(quantity: &Quantity, unit: Option<&str>) -> Option<f64> {
+    match (quantity.unit(), unit) {
+        // Same unit, even if the converter doesn't know it (e.g. "clove")
+        (Some(from), Some(to)) if from == to => quantity_number(quantity.value()),
+        (_, Some(to)) => {
+            let converted = convert_quantity(quantity.clone(), ConvertTo::from(to), to).ok()?;
+            quantity_number(converted.value())
+        }
+        // Priced per item: a count, possibly described by a unit like "large"
+        (Some(from), None) if get_unit_converter().find_unit(from).is_some() => None,
+        (_, None) => quantity_number(quantity.value()),
+    }
+}
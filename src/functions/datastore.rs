@@ -1,23 +1,331 @@
+use crate::warning::{Warning, WarningKind, emit, is_strict};
+use minijinja::value::{Enumerator, Kwargs, Object};
 use minijinja::{Error as MiniError, ErrorKind, State, Value as MiniValue};
-use serde::Deserialize;
-use yaml_datastore::Datastore;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value as YamlValue;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use yaml_datastore::Error as DatastoreError;
+use yaml_datastore::keypath::{DEFAULT_EXTENSIONS, KeyPath};
 
 fn non_key_error(message: &str) -> MiniError {
     MiniError::new(ErrorKind::NonKey, message.to_owned())
 }
 
-pub fn get_from_datastore(state: &State, keypath: &str) -> Result<MiniValue, MiniError> {
-    // Lookup datastore. If it exists, convert it from Value to Datastore. Then get the key.
-    // This is kinda terse, but the expanded version isn't really any better IMO.
-    let datastore = state
+/// Datastores searched in order, so earlier ones override later ones key by key.
+///
+/// It is opened once per renderer and passed to templates as the `datastore` object, so
+/// `datastore.eggs.meta.density` works like `db('eggs.meta.density')`. Each YAML file is read
+/// and parsed once, on first use.
+#[derive(Debug)]
+pub(crate) struct LayeredDatastore {
+    roots: Vec<PathBuf>,
+    /// Parsed YAML files by path, `None` for files that are missing or invalid
+    files: Mutex<HashMap<PathBuf, Option<Arc<YamlValue>>>>,
+}
+
+impl LayeredDatastore {
+    /// Layer the datastores at `roots`, or `None` if there are none.
+    pub(crate) fn open(roots: &[PathBuf]) -> Option<Self> {
+        (!roots.is_empty()).then(|| Self {
+            roots: roots.to_vec(),
+            files: Mutex::default(),
+        })
+    }
+
+    /// Get the value at `keypath` from the first datastore that has it.
+    pub(crate) fn get<T: DeserializeOwned>(&self, keypath: &str) -> Result<T, DatastoreError> {
+        let (value, _) = self.get_with_source(keypath)?;
+        Ok(serde_yaml::from_value(value)?)
+    }
+
+    /// Get the value at `keypath` along with the root of the datastore it came from.
+    ///
+    /// As in [`yaml_datastore::Datastore::get`], `eggs.meta.density` is looked up as the
+    /// `density` key of `eggs/meta.yml`, then as the `meta.density` key of `eggs.yml`, and
+    /// so on.
+    fn get_with_source(&self, keypath: &str) -> Result<(YamlValue, &Path), DatastoreError> {
+        let keypath = KeyPath::try_from(keypath)?;
+        for root in &self.roots {
+            for (path, keys) in keypath.iter() {
+                let Some(data) = self.read(root.join(path)) else {
+                    continue;
+                };
+                if let Some(value) = keys
+                    .iter()
+                    .try_fold(data.as_ref(), |value, key| value.get(key))
+                {
+                    return Ok((value.clone(), root));
+                }
+            }
+        }
+        Err(DatastoreError::KeyNotFound)
+    }
+
+    /// Read and parse a YAML file, or get it from the cache if it was read before.
+    fn read(&self, path: PathBuf) -> Option<Arc<YamlValue>> {
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        files
+            .entry(path)
+            .or_insert_with_key(|path| {
+                let content = std::fs::read_to_string(path).ok()?;
+                serde_yaml::from_str(&content).ok().map(Arc::new)
+            })
+            .clone()
+    }
+
+    /// The names one level below `prefix`, sorted, or the top-level names if it is empty.
+    ///
+    /// For a directory these are its subdirectories and YAML files, from every datastore.
+    /// For a mapping they are its keys, from the datastore `get` would read it from.
+    fn keys(&self, prefix: &str) -> Result<BTreeSet<String>, DatastoreError> {
+        let components = if prefix.is_empty() {
+            Vec::new()
+        } else {
+            KeyPath::try_from(prefix)?
+                .components()
+                .into_iter()
+                .map(String::from)
+                .collect()
+        };
+
+        let mut keys = BTreeSet::new();
+        for root in &self.roots {
+            let dir: PathBuf = std::iter::once(root.as_path())
+                .chain(components.iter().map(Path::new))
+                .collect();
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                let is_yaml = path
+                    .extension()
+                    .is_some_and(|ext| DEFAULT_EXTENSIONS.iter().any(|yaml| ext == *yaml));
+                if !path.is_dir() && !is_yaml {
+                    continue;
+                }
+                if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                    keys.insert(name.to_string());
+                }
+            }
+        }
+
+        if !prefix.is_empty()
+            && let Ok(serde_yaml::Value::Mapping(mapping)) = self.get(prefix)
+        {
+            keys.extend(
+                mapping
+                    .keys()
+                    .filter_map(|key| key.as_str().map(String::from)),
+            );
+        }
+        Ok(keys)
+    }
+
+    /// The datastore roots, quoted and comma-separated, for messages.
+    /// The files in any datastore that could hold `keypath`, quoted for error messages.
+    fn describe_files(&self, keypath: &str) -> String {
+        let Ok(keypath) = KeyPath::try_from(keypath) else {
+            return "no file".to_string();
+        };
+        let files: Vec<_> = self
+            .roots
+            .iter()
+            .flat_map(|root| keypath.iter().map(move |(path, _)| root.join(path)))
+            .filter(|path| path.is_file())
+            .map(|path| format!("'{}'", path.display()))
+            .collect();
+        if files.is_empty() {
+            "no file".to_string()
+        } else {
+            files.join(", ")
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.roots
+            .iter()
+            .map(|root| format!("'{}'", root.display()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Object for LayeredDatastore {
+    fn get_value(self: &Arc<Self>, key: &MiniValue) -> Option<MiniValue> {
+        attribute(self, key.as_str()?.to_string())
+    }
+
+    fn enumerate(self: &Arc<Self>) -> Enumerator {
+        enumerate_keys(self, "")
+    }
+}
+
+/// A directory of the datastore, reached by attribute access like `datastore.eggs`.
+#[derive(Debug)]
+struct DatastoreDirectory {
+    datastore: Arc<LayeredDatastore>,
+    keypath: String,
+}
+
+impl Object for DatastoreDirectory {
+    fn get_value(self: &Arc<Self>, key: &MiniValue) -> Option<MiniValue> {
+        attribute(
+            &self.datastore,
+            format!("{}.{}", self.keypath, key.as_str()?),
+        )
+    }
+
+    fn enumerate(self: &Arc<Self>) -> Enumerator {
+        enumerate_keys(&self.datastore, &self.keypath)
+    }
+}
+
+/// Look up `keypath` for attribute access: its value, or an object for further access if
+/// it names a directory in any datastore.
+fn attribute(datastore: &Arc<LayeredDatastore>, keypath: String) -> Option<MiniValue> {
+    if let Ok(value) = datastore.get(&keypath) {
+        return Some(value);
+    }
+    let components = KeyPath::try_from(keypath.as_str())
+        .ok()?
+        .components()
+        .join("/");
+    let is_directory = datastore
+        .roots
+        .iter()
+        .any(|root| root.join(&components).is_dir());
+    is_directory.then(|| {
+        MiniValue::from_object(DatastoreDirectory {
+            datastore: Arc::clone(datastore),
+            keypath,
+        })
+    })
+}
+
+fn enumerate_keys(datastore: &LayeredDatastore, prefix: &str) -> Enumerator {
+    let keys = datastore.keys(prefix).unwrap_or_default();
+    Enumerator::Values(keys.into_iter().map(MiniValue::from).collect())
+}
+
+/// Get the datastore configured for this render.
+pub(crate) fn datastore_from_state(state: &State) -> Result<Arc<LayeredDatastore>, MiniError> {
+    state
         .lookup("datastore")
-        .ok_or(non_key_error("bad datastore"))
-        .and_then(|x| Option::<Datastore>::deserialize(x)?.ok_or(non_key_error("no datastore")))?;
+        .and_then(|datastore| datastore.downcast_object::<LayeredDatastore>())
+        .ok_or(non_key_error("no datastore"))
+}
 
-    if let Ok(value) = datastore.get(keypath) {
-        Ok(value)
+/// Get the value at `keypath` from the datastore.
+///
+/// A missing key gives `default` if one is passed. Otherwise it records a warning and gives
+/// an empty string, or fails in strict mode.
+///
+/// # Template Usage
+/// ```jinja
+/// {{ db('eggs.meta.density') }}
+/// {{ db('eggs.shopping.brand', default='n/a') }}
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if no datastore is configured, or in strict mode if the key is missing
+/// and no default is passed. The error names the datastore files that were looked in.
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_from_datastore(
+    state: &State,
+    keypath: &str,
+    kwargs: Kwargs,
+) -> Result<MiniValue, MiniError> {
+    let default = if kwargs.has("default") {
+        Some(kwargs.get::<MiniValue>("default")?)
     } else {
-        eprintln!("Warning: key '{keypath}' not found in datastore, using empty value");
-        Ok(MiniValue::from(""))
+        None
+    };
+    kwargs.assert_all_used()?;
+    let datastore = datastore_from_state(state)?;
+
+    match (datastore.get(keypath), default) {
+        (Ok(value), _) | (Err(_), Some(value)) => Ok(value),
+        (Err(e), None) if is_strict(state) => Err(non_key_error(&format!(
+            "key '{keypath}' not found in datastore {} (looked in {}): {e}",
+            datastore.describe(),
+            datastore.describe_files(keypath)
+        ))),
+        (Err(_), None) => {
+            emit(
+                state,
+                Warning::new(
+                    WarningKind::Datastore,
+                    format!("key '{keypath}' not found in datastore, using empty value"),
+                ),
+            );
+            Ok(MiniValue::from(""))
+        }
     }
 }
+
+/// Get the root of the datastore that `db(keypath)` would read `keypath` from.
+///
+/// With several datastores configured, this tells which one a value came from. Returns
+/// none if no datastore has the key.
+///
+/// # Template Usage
+/// ```jinja
+/// {{ db('eggs.shopping.price') }} (from {{ db_source('eggs.shopping.price') }})
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if no datastore is configured.
+pub fn datastore_source(state: &State, keypath: &str) -> Result<MiniValue, MiniError> {
+    let datastore = datastore_from_state(state)?;
+    let source = datastore
+        .get_with_source(keypath)
+        .ok()
+        .map(|(_, root)| root.display().to_string());
+    Ok(MiniValue::from(source))
+}
+
+/// Check whether any datastore has a value at `keypath`.
+///
+/// # Template Usage
+/// ```jinja
+/// {% if db_exists('eggs.shopping') %}...{% endif %}
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if no datastore is configured.
+pub fn datastore_exists(state: &State, keypath: &str) -> Result<bool, MiniError> {
+    let datastore = datastore_from_state(state)?;
+    Ok(datastore.get_with_source(keypath).is_ok())
+}
+
+/// List the names one level below `prefix` in the datastore, sorted.
+///
+/// With an empty prefix these are the top-level directories and files, usually ingredient
+/// names. For a directory they are its subdirectories and YAML files, and for a file or a
+/// mapping inside one, its keys. Directories are listed from every datastore.
+///
+/// # Template Usage
+/// ```jinja
+/// {% for name in db_keys('') if db_exists(name ~ '.shopping') %}
+/// - {{ name }}: {{ db(name ~ '.shopping.price') }}
+/// {% endfor %}
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if no datastore is configured or `prefix` is not a valid key path.
+pub fn datastore_keys(state: &State, prefix: &str) -> Result<MiniValue, MiniError> {
+    let datastore = datastore_from_state(state)?;
+    let keys = datastore.keys(prefix).map_err(|e| {
+        MiniError::new(
+            ErrorKind::InvalidOperation,
+            format!("invalid key path '{prefix}': {e}"),
+        )
+    })?;
+    Ok(MiniValue::from_iter(keys))
+}
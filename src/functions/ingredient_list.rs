@@ -1,5 +1,6 @@
 use crate::model::{IngredientList as ModelIngredientList, quantity_from_value};
 use crate::parser::{get_converter, get_parser};
+use crate::warning::{Warning, WarningKind, emit};
 use anyhow::{Context, Result, anyhow};
 use cooklang::{
     ingredient_list::IngredientList,
@@ -39,6 +40,7 @@ pub fn get_ingredient_list(
 
     // Process all ingredients directly
     process_ingredients(
+        state,
         ingredients,
         &mut list,
         &mut seen,
@@ -69,6 +71,7 @@ fn extract_base_path(state: &State) -> String {
 
 /// Process ingredients from minijinja Values
 fn process_ingredients(
+    state: &State,
     ingredients: &Value,
     list: &mut IngredientList,
     seen: &mut BTreeMap<String, usize>,
@@ -87,6 +90,7 @@ fn process_ingredients(
         if is_reference && expand_references {
             // Handle recipe reference only if expansion is enabled
             process_recipe_reference(
+                state,
                 &item,
                 list,
                 seen,
@@ -151,6 +155,7 @@ fn process_regular_ingredient(
 
 /// Process a recipe reference
 fn process_recipe_reference(
+    state: &State,
     item: &Value,
     list: &mut IngredientList,
     seen: &mut BTreeMap<String, usize>,
@@ -202,10 +207,11 @@ fn process_recipe_reference(
     }
 
     // Include warnings if present
-    if parse_result.report().has_warnings() {
-        for warning in parse_result.report().warnings() {
-            eprintln!("Warning in '{reference_path}': {warning}");
-        }
+    for warning in parse_result.report().warnings() {
+        emit(
+            state,
+            Warning::from_diag(WarningKind::Reference, warning).in_source(&reference_path),
+        );
     }
 
     let mut recipe = parse_result
@@ -274,6 +280,7 @@ fn process_recipe_reference(
         let nested_value = Value::from_iter(map);
         let nested_ingredients = Value::from(vec![nested_value]);
         process_ingredients(
+            state,
             &nested_ingredients,
             list,
             seen,
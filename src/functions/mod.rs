@@ -1,14 +1,18 @@
 pub mod aisle;
+pub mod cost;
 pub mod datastore;
 pub mod ingredient_list;
 pub mod numeric;
+pub mod nutrition;
 pub mod pantry;
 
 pub use aisle::aisled;
-pub use datastore::get_from_datastore;
+pub use cost::cost;
+pub use datastore::{datastore_exists, datastore_keys, datastore_source, get_from_datastore};
 pub use ingredient_list::get_ingredient_list;
 pub use numeric::{
     number_to_currency, number_to_human, number_to_human_size, number_to_percentage,
     number_with_delimiter, number_with_precision,
 };
-pub use pantry::{excluding_pantry, from_pantry};
+pub use nutrition::nutrition;
+pub use pantry::{excluding_pantry, expiring_pantry, from_pantry, low_stock_pantry, still_needed};
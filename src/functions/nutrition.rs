@@ -0,0 +1,154 @@
+use super::datastore::{LayeredDatastore, datastore_from_state};
+use crate::model::{ingredient_quantities, quantity_number};
+use crate::parser::get_unit_converter;
+use cooklang::convert::PhysicalQuantity;
+use cooklang::quantity::Quantity;
+use minijinja::value::Kwargs;
+use minijinja::{Error, State, Value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Nutrition facts for a list of ingredients, as returned to the template.
+#[derive(Debug, Default, Serialize)]
+struct Nutrition {
+    total: BTreeMap<String, f64>,
+    servings: Option<f64>,
+    per_serving: Option<BTreeMap<String, f64>>,
+    ingredients: Vec<IngredientNutrition>,
+    unresolved: Vec<String>,
+}
+
+/// Nutrition facts for a single ingredient.
+#[derive(Debug, Serialize)]
+struct IngredientNutrition {
+    name: String,
+    grams: f64,
+    nutrients: BTreeMap<String, f64>,
+}
+
+/// Calculate nutrition facts for a list of ingredients from the datastore.
+///
+/// Each ingredient needs a `nutrition.yml` in its datastore directory with nutrient values per
+/// 100 g. Quantities are converted to grams; volumes are converted using the `density` (in
+/// g/ml) from the ingredient's `meta.yml`. Ranges count as their midpoint.
+///
+/// # Arguments
+/// * `ingredients` - The recipe's ingredients or the result of `get_ingredient_list`
+/// * `servings` - Optional keyword argument with the number of servings. Defaults to the
+///   recipe's `servings` metadata.
+///
+/// # Returns
+/// A map with:
+/// * `total` - nutrient totals for all resolved ingredients
+/// * `servings` and `per_serving` - the servings and totals divided by them, if known
+/// * `ingredients` - for each resolved ingredient, its `name`, `grams` and `nutrients`
+/// * `unresolved` - names of ingredients with no nutrition data, or whose quantity could not
+///   be converted to grams
+///
+/// # Template Usage
+/// ```jinja
+/// {% set facts = nutrition(ingredients) %}
+/// Calories per serving: {{ facts.per_serving.calories | round }}
+/// {% if facts.unresolved %}Missing data for: {{ facts.unresolved | join(", ") }}{% endif %}
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if no datastore is configured.
+#[allow(clippy::needless_pass_by_value)]
+pub fn nutrition(state: &State, ingredients: Value, kwargs: Kwargs) -> Result<Value, Error> {
+    let datastore = datastore_from_state(state)?;
+    let servings = match kwargs.get::<Option<Value>>("servings")? {
+        Some(servings) => servings_from_value(&servings),
+        None => state
+            .lookup("metadata")
+            .and_then(|m| m.get_attr("servings").ok())
+            .and_then(|s| servings_from_value(&s)),
+    };
+    kwargs.assert_all_used()?;
+
+    let mut result = Nutrition {
+        servings,
+        ..Nutrition::default()
+    };
+
+    for ingredient in ingredients.try_iter()? {
+        let Some(name) = ingredient
+            .get_attr("name")
+            .ok()
+            .and_then(|n| n.as_str().map(String::from))
+        else {
+            continue;
+        };
+
+        match ingredient_nutrition(&datastore, &name, &ingredient_quantities(&ingredient)) {
+            Some(nutrition) => {
+                for (nutrient, amount) in &nutrition.nutrients {
+                    *result.total.entry(nutrient.clone()).or_default() += amount;
+                }
+                result.ingredients.push(nutrition);
+            }
+            None => result.unresolved.push(name),
+        }
+    }
+
+    result.per_serving = servings.map(|servings| {
+        result
+            .total
+            .iter()
+            .map(|(nutrient, amount)| (nutrient.clone(), amount / servings))
+            .collect()
+    });
+
+    Ok(Value::from_serialize(&result))
+}
+
+/// Resolve the nutrients of one ingredient, or `None` if its data or quantity is missing.
+fn ingredient_nutrition(
+    datastore: &LayeredDatastore,
+    name: &str,
+    quantities: &[Quantity],
+) -> Option<IngredientNutrition> {
+    let per_100g: BTreeMap<String, f64> = datastore.get(&format!("{name}.nutrition")).ok()?;
+    if quantities.is_empty() {
+        return None;
+    }
+
+    let density = datastore.get::<f64>(&format!("{name}.meta.density")).ok();
+    let grams = quantities
+        .iter()
+        .map(|q| quantity_in_grams(q, density))
+        .sum::<Option<f64>>()?;
+
+    Some(IngredientNutrition {
+        name: name.to_string(),
+        grams,
+        nutrients: per_100g
+            .into_iter()
+            .map(|(nutrient, amount)| (nutrient, amount * grams / 100.0))
+            .collect(),
+    })
+}
+
+/// Convert a quantity to grams. Volumes need a density in g/ml.
+fn quantity_in_grams(quantity: &Quantity, density: Option<f64>) -> Option<f64> {
+    let converter = get_unit_converter();
+    let unit = converter.find_unit(quantity.unit()?)?;
+    let (target, grams_per_unit) = match unit.physical_quantity {
+        PhysicalQuantity::Mass => ("g", 1.0),
+        PhysicalQuantity::Volume => ("ml", density?),
+        _ => return None,
+    };
+
+    let mut quantity = quantity.clone();
+    quantity.convert(target, converter).ok()?;
+    quantity_number(quantity.value()).map(|n| n * grams_per_unit)
+}
+
+/// Read a number of servings from a number or a numeric string.
+fn servings_from_value(value: &Value) -> Option<f64> {
+    f64::try_from(value.clone())
+        .ok()
+        .or_else(|| value.as_str()?.trim().parse().ok())
+        .filter(|servings| *servings > 0.0)
+}
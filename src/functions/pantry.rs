@@ -1,4 +1,20 @@
-use minijinja::{State, Value};
+use crate::filters::convert::convert_quantity;
+use crate::model::{
+    GroupedQuantity, IngredientListItem, Quantity, ingredient_quantities, parse_quantity,
+    quantity_number,
+};
+use crate::parser::get_unit_converter;
+use crate::warning::{Warning, WarningKind, emit, is_strict};
+use cooklang::convert::ConvertTo;
+use cooklang::pantry::{PantryConf, PantryItem};
+use cooklang::quantity::{
+    GroupedQuantity as CooklangGroupedQuantity, Quantity as CooklangQuantity,
+    Value as QuantityValue,
+};
+use minijinja::value::{Kwargs, Object};
+use minijinja::{Error, ErrorKind::InvalidOperation, State, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Filter ingredients to exclude items that are already in the pantry.
 ///
@@ -19,46 +35,37 @@ use minijinja::{State, Value};
 /// - {{ ingredient.name }}: {{ ingredient.quantity }}
 /// {% endfor %}
 /// ```
-pub fn excluding_pantry(state: &State, ingredients: Value) -> Value {
-    // Try to get pantry content from state
-    let pantry_content = state
-        .lookup("pantry_content")
-        .and_then(|v| v.as_str().map(String::from));
-
-    if let Some(content) = pantry_content {
-        // Parse the pantry configuration
-        let parse_result = cooklang::pantry::parse_lenient(&content);
-
-        if let Some(pantry_conf) = parse_result.output() {
-            // Filter ingredients - keep only those NOT in pantry
-            let mut filtered = Vec::new();
-
-            if let Ok(iter) = ingredients.try_iter() {
-                for item in iter {
-                    // Get ingredient name
-                    if let Ok(name) = item.get_attr("name")
-                        && let Some(name_str) = name.as_str()
-                    {
-                        // Check if this ingredient is NOT in the pantry
-                        let in_pantry = pantry_conf.has_ingredient(name_str);
-
-                        if !in_pantry {
-                            filtered.push(item);
-                        }
+///
+/// # Errors
+///
+/// In strict mode, returns an error if no pantry configuration is provided.
+pub fn excluding_pantry(state: &State, ingredients: Value) -> Result<Value, Error> {
+    // Try to get the parsed pantry configuration from state
+    Ok(if let Some(pantry_conf) = pantry_config(state)? {
+        // Filter ingredients - keep only those NOT in pantry
+        let mut filtered = Vec::new();
+
+        if let Ok(iter) = ingredients.try_iter() {
+            for item in iter {
+                // Get ingredient name
+                if let Ok(name) = item.get_attr("name")
+                    && let Some(name_str) = name.as_str()
+                {
+                    // Check if this ingredient is NOT in the pantry
+                    let in_pantry = pantry_conf.0.has_ingredient(name_str);
+
+                    if !in_pantry {
+                        filtered.push(item);
                     }
                 }
             }
-
-            Value::from(filtered)
-        } else {
-            // Failed to parse pantry configuration
-            eprintln!("Warning: Failed to parse pantry configuration. Returning all ingredients.");
-            ingredients
         }
+
+        Value::from(filtered)
     } else {
         // No pantry configuration provided - return all ingredients
         ingredients
-    }
+    })
 }
 
 /// Filter ingredients to include only items that are in the pantry.
@@ -80,45 +87,493 @@ pub fn excluding_pantry(state: &State, ingredients: Value) -> Value {
 /// - {{ ingredient.name }}: {{ ingredient.quantity }}
 /// {% endfor %}
 /// ```
+///
+/// # Errors
+///
+/// In strict mode, returns an error if no pantry configuration is provided.
 #[allow(clippy::needless_pass_by_value)]
-pub fn from_pantry(state: &State, ingredients: Value) -> Value {
-    // Try to get pantry content from state
-    let pantry_content = state
-        .lookup("pantry_content")
-        .and_then(|v| v.as_str().map(String::from));
-
-    if let Some(content) = pantry_content {
-        // Parse the pantry configuration
-        let parse_result = cooklang::pantry::parse_lenient(&content);
-
-        if let Some(pantry_conf) = parse_result.output() {
-            // Filter ingredients - keep only those IN pantry
-            let mut filtered = Vec::new();
-
-            if let Ok(iter) = ingredients.try_iter() {
-                for item in iter {
-                    // Get ingredient name
-                    if let Ok(name) = item.get_attr("name")
-                        && let Some(name_str) = name.as_str()
-                    {
-                        // Check if this ingredient IS in the pantry
-                        let in_pantry = pantry_conf.has_ingredient(name_str);
-
-                        if in_pantry {
-                            filtered.push(item);
-                        }
+pub fn from_pantry(state: &State, ingredients: Value) -> Result<Value, Error> {
+    // Try to get the parsed pantry configuration from state
+    Ok(if let Some(pantry_conf) = pantry_config(state)? {
+        // Filter ingredients - keep only those IN pantry
+        let mut filtered = Vec::new();
+
+        if let Ok(iter) = ingredients.try_iter() {
+            for item in iter {
+                // Get ingredient name
+                if let Ok(name) = item.get_attr("name")
+                    && let Some(name_str) = name.as_str()
+                {
+                    // Check if this ingredient IS in the pantry
+                    let in_pantry = pantry_conf.0.has_ingredient(name_str);
+
+                    if in_pantry {
+                        filtered.push(item);
                     }
                 }
             }
-
-            Value::from(filtered)
-        } else {
-            // Failed to parse pantry configuration
-            eprintln!("Warning: Failed to parse pantry configuration. Returning empty list.");
-            Value::from(Vec::<Value>::new())
         }
+
+        Value::from(filtered)
     } else {
         // No pantry configuration provided - return empty list
         Value::from(Vec::<Value>::new())
+    })
+}
+
+/// Subtract pantry stock from a list of ingredients and return what still needs to be bought.
+///
+/// Unlike `excluding_pantry`, this looks at the quantities recorded in the pantry. Stock is
+/// converted to each ingredient's unit, so 500 g of flour in the pantry leaves 1.5 kg of a
+/// 2 kg requirement. Ingredients are kept while any amount is still needed. Pantry items
+/// without a quantity count as enough, and quantities that can't be compared with the stock
+/// (text values or incompatible units) are kept in full.
+///
+/// # Arguments
+/// * `ingredients` - The recipe's ingredients or the result of `get_ingredient_list`
+///
+/// # Returns
+/// A list of items like those from `get_ingredient_list`, each with a `name` and the
+/// remaining `quantities`. If no pantry configuration is available, nothing is subtracted.
+///
+/// # Template Usage
+/// ```jinja
+/// # Need to buy
+/// {% for item in still_needed(get_ingredient_list(ingredients)) %}
+/// - {{ item.name }}: {{ item.quantities }}
+/// {% endfor %}
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `ingredients` is not a list. In strict mode, also if no pantry
+/// configuration is provided.
+#[allow(clippy::needless_pass_by_value)]
+pub fn still_needed(state: &State, ingredients: Value) -> Result<Value, Error> {
+    let pantry_conf = pantry_config(state)?;
+
+    let mut needed = Vec::new();
+    for item in ingredients.try_iter()? {
+        let Some(name) = item
+            .get_attr("name")
+            .ok()
+            .and_then(|n| n.as_str().map(String::from))
+        else {
+            continue;
+        };
+
+        let quantities = ingredient_quantities(&item);
+        let remaining = match pantry_conf
+            .as_ref()
+            .and_then(|p| p.0.find_ingredient(&name))
+        {
+            None => quantities,
+            Some((_, pantry_item)) => {
+                // Listed without a quantity, so assume there's enough
+                let Some(stock) = pantry_item.parsed_quantity() else {
+                    continue;
+                };
+                let remaining = subtract_stock(quantities, stock);
+                if remaining.is_empty() {
+                    continue;
+                }
+                remaining
+            }
+        };
+
+        let mut grouped = CooklangGroupedQuantity::empty();
+        for quantity in &remaining {
+            grouped.add(quantity, get_unit_converter());
+        }
+        needed.push(Value::from(IngredientListItem::new(
+            name,
+            GroupedQuantity::from(grouped),
+        )));
+    }
+
+    Ok(Value::from(needed))
+}
+
+/// Use up `stock`, a pantry amount and unit, against the needed quantities and return what
+/// is left to buy.
+fn subtract_stock(
+    quantities: Vec<CooklangQuantity>,
+    (mut stock, stock_unit): (f64, String),
+) -> Vec<CooklangQuantity> {
+    let stock_unit = Some(stock_unit).filter(|u| !u.is_empty());
+    let mut remaining = Vec::new();
+
+    for quantity in quantities {
+        let Some(amount) = quantity_number(quantity.value()) else {
+            remaining.push(quantity);
+            continue;
+        };
+        let Some(available) = stock_in_unit(stock, stock_unit.as_deref(), quantity.unit()) else {
+            remaining.push(quantity);
+            continue;
+        };
+
+        let used = amount.min(available);
+        if available > 0.0 {
+            stock *= 1.0 - used / available;
+        }
+        if amount > used {
+            remaining.push(CooklangQuantity::new(
+                QuantityValue::from(amount - used),
+                quantity.unit().map(String::from),
+            ));
+        }
+    }
+
+    remaining
+}
+
+/// Express the pantry stock in the unit of a needed quantity, if they are comparable.
+fn stock_in_unit(stock: f64, stock_unit: Option<&str>, unit: Option<&str>) -> Option<f64> {
+    match (stock_unit, unit) {
+        (None, None) => Some(stock),
+        (Some(from), Some(to)) if from.eq_ignore_ascii_case(to) => Some(stock),
+        (Some(from), Some(to)) => {
+            let stock = CooklangQuantity::new(QuantityValue::from(stock), Some(from.to_string()));
+            let converted = convert_quantity(stock, ConvertTo::from(to), to).ok()?;
+            quantity_number(converted.value())
+        }
+        _ => None,
+    }
+}
+
+/// A parsed pantry configuration, shared by every pantry function while rendering.
+#[derive(Debug, Default)]
+pub(crate) struct PantryConfig(PantryConf);
+
+impl PantryConfig {
+    /// Parse a pantry configuration file, collecting its warnings.
+    ///
+    /// A file that can't be parsed gives an empty pantry and a single warning.
+    pub(crate) fn parse(content: &str, warnings: &mut Vec<Warning>) -> Self {
+        let result = cooklang::pantry::parse_lenient(content);
+        warnings.extend(
+            result
+                .report()
+                .iter()
+                .map(|w| Warning::from_diag(WarningKind::Pantry, w)),
+        );
+
+        if let Some(pantry_conf) = result.into_output() {
+            Self(pantry_conf)
+        } else {
+            warnings.push(Warning::new(
+                WarningKind::Pantry,
+                "Failed to parse pantry configuration. Treating the pantry as empty.",
+            ));
+            Self::default()
+        }
+    }
+
+    /// The whole pantry as a map of sections, in file order, to their items.
+    ///
+    /// This is the `pantry` template variable. Each item has a `name`, `section`, `quantity`,
+    /// `bought`, `expire` and `low` (each `none` if not set) and `is_low`. `quantity` and `low`
+    /// are quantities, like an ingredient's.
+    pub(crate) fn sections(&self) -> Value {
+        self.0
+            .sections
+            .iter()
+            .map(|(section, items)| {
+                let entries: Vec<_> = items
+                    .iter()
+                    .map(|item| PantryEntry::new(section, item).into_value())
+                    .collect();
+                (section.as_str(), Value::from(entries))
+            })
+            .collect()
+    }
+}
+
+impl Object for PantryConfig {}
+
+/// A pantry item as shown to templates, e.g. in the `pantry` variable.
+#[derive(Debug)]
+struct PantryEntry<'a> {
+    name: &'a str,
+    section: &'a str,
+    quantity: Option<Quantity>,
+    bought: Option<&'a str>,
+    expire: Option<&'a str>,
+    low: Option<Quantity>,
+    is_low: bool,
+    days_left: Option<i64>,
+}
+
+impl<'a> PantryEntry<'a> {
+    fn new(section: &'a str, item: &'a PantryItem) -> Self {
+        Self {
+            name: item.name(),
+            section,
+            quantity: item.quantity().map(pantry_quantity),
+            bought: item.bought(),
+            expire: item.expire(),
+            low: item.low().map(pantry_quantity),
+            is_low: is_low(item),
+            days_left: None,
+        }
+    }
+
+    /// The entry as a template value, with `days_left` only when it is set.
+    fn into_value(self) -> Value {
+        let mut entry = vec![
+            ("name", Value::from(self.name)),
+            ("section", Value::from(self.section)),
+            ("quantity", Value::from(self.quantity)),
+            ("bought", Value::from(self.bought)),
+            ("expire", Value::from(self.expire)),
+            ("low", Value::from(self.low)),
+            ("is_low", Value::from(self.is_low)),
+        ];
+        if let Some(days_left) = self.days_left {
+            entry.push(("days_left", Value::from(days_left)));
+        }
+        entry.into_iter().collect()
+    }
+}
+
+/// Parse a pantry quantity such as `50%g` into a [`Quantity`] for templates.
+fn pantry_quantity(quantity: &str) -> Quantity {
+    let (value, unit) = quantity.split_once('%').unwrap_or((quantity, ""));
+    let unit = Some(unit.trim())
+        .filter(|u| !u.is_empty())
+        .map(String::from);
+    Quantity::from(parse_quantity(value.trim(), unit))
+}
+
+/// List pantry items that expire within a number of days, soonest first.
+///
+/// Items that have already expired are included, with a negative `days_left`. Dates in the
+/// pantry can be written as `DD.MM.YYYY`, `DD/MM/YYYY` or `YYYY-MM-DD`.
+///
+/// # Arguments
+/// * `days` - Optional keyword argument with how many days ahead to look. Defaults to 7.
+/// * `today` - Optional keyword argument with the date to count from. Defaults to the
+///   current date.
+///
+/// # Returns
+/// A list of pantry items, as in the `pantry` variable, with the `days_left` until they
+/// expire. If no pantry configuration is available, returns an empty list.
+///
+/// # Template Usage
+/// ```jinja
+/// # Use soon
+/// {% for item in expiring_pantry(days=3) %}
+/// - {{ item.name }} ({{ item.section }}): expires {{ item.expire }}
+/// {% endfor %}
+/// ```
+///
+/// # Errors
+///
+/// Returns an `InvalidOperation` error if `today` is not a valid date. In strict mode, also
+/// if no pantry configuration is provided.
+#[allow(clippy::needless_pass_by_value)]
+pub fn expiring_pantry(state: &State, kwargs: Kwargs) -> Result<Value, Error> {
+    let days = kwargs.get::<Option<i64>>("days")?.unwrap_or(7);
+    let today = match kwargs.get::<Option<&str>>("today")? {
+        Some(date) => parse_date(date).ok_or_else(|| {
+            Error::new(
+                InvalidOperation,
+                format!("invalid date '{date}', expected DD.MM.YYYY or YYYY-MM-DD"),
+            )
+        })?,
+        None => current_day(),
+    };
+    kwargs.assert_all_used()?;
+
+    let Some(pantry_conf) = pantry_config(state)? else {
+        return Ok(Value::from(Vec::<Value>::new()));
+    };
+
+    let mut expiring = Vec::new();
+    for (section, items) in &pantry_conf.0.sections {
+        for item in items {
+            let Some(expire) = item.expire() else {
+                continue;
+            };
+            let Some(expire_day) = parse_date(expire) else {
+                emit(
+                    state,
+                    Warning::new(
+                        WarningKind::Pantry,
+                        format!("Invalid expiry date '{expire}' for '{}'", item.name()),
+                    ),
+                );
+                continue;
+            };
+
+            let days_left = expire_day - today;
+            if days_left <= days {
+                expiring.push(PantryEntry {
+                    days_left: Some(days_left),
+                    ..PantryEntry::new(section, item)
+                });
+            }
+        }
+    }
+    expiring.sort_by_key(|entry| entry.days_left);
+
+    Ok(Value::from(
+        expiring
+            .into_iter()
+            .map(PantryEntry::into_value)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// List pantry items at or below their `low` stock threshold.
+///
+/// The quantity and threshold are compared after converting to the same unit, so an item
+/// with `quantity = "400%g"` and `low = "1%kg"` is low on stock.
+///
+/// # Returns
+/// A list of pantry items, as in the `pantry` variable, in file order. If no pantry
+/// configuration is available, returns an empty list.
+///
+/// # Template Usage
+/// ```jinja
+/// # Restock
+/// {% for item in low_stock_pantry() %}
+/// - {{ item.name }}: {{ item.quantity }} left
+/// {% endfor %}
+/// ```
+///
+/// # Errors
+///
+/// In strict mode, returns an error if no pantry configuration is provided.
+pub fn low_stock_pantry(state: &State) -> Result<Value, Error> {
+    let Some(pantry_conf) = pantry_config(state)? else {
+        return Ok(Value::from(Vec::<Value>::new()));
+    };
+
+    let low_stock: Vec<Value> = pantry_conf
+        .0
+        .sections
+        .iter()
+        .flat_map(|(section, items)| {
+            items
+                .iter()
+                .filter(|item| is_low(item))
+                .map(|item| PantryEntry::new(section, item).into_value())
+        })
+        .collect();
+
+    Ok(Value::from(low_stock))
+}
+
+/// Get the parsed pantry configuration from state, if one was provided.
+///
+/// In strict mode, a missing pantry configuration is an error instead.
+fn pantry_config(state: &State) -> Result<Option<Arc<PantryConfig>>, Error> {
+    let pantry_config = state
+        .lookup("pantry_config")
+        .and_then(|config| config.downcast_object::<PantryConfig>());
+    if pantry_config.is_none() && is_strict(state) {
+        return Err(Error::new(
+            InvalidOperation,
+            "no pantry configuration provided, use Config::builder().pantry_path(path)",
+        ));
+    }
+    Ok(pantry_config)
+}
+
+/// Whether an item's quantity is at or below its `low` threshold, converting units if needed.
+fn is_low(item: &PantryItem) -> bool {
+    let Some((amount, unit)) = item.parsed_quantity() else {
+        return false;
+    };
+    let Some((low, low_unit)) = item.low().and_then(parse_pantry_quantity) else {
+        return false;
+    };
+
+    let unit = Some(unit).filter(|u| !u.is_empty());
+    let low_unit = Some(low_unit).filter(|u| !u.is_empty());
+    stock_in_unit(low, low_unit.as_deref(), unit.as_deref()).is_some_and(|low| amount <= low)
+}
+
+/// Parse a pantry quantity such as `500%ml` or `5` into an amount and a unit.
+fn parse_pantry_quantity(quantity: &str) -> Option<(f64, String)> {
+    let (amount, unit) = quantity.split_once('%').unwrap_or((quantity, ""));
+    Some((amount.trim().parse().ok()?, unit.trim().to_lowercase()))
+}
+
+/// Parse a date written as `DD.MM.YYYY`, `DD/MM/YYYY` or `YYYY-MM-DD` into days since
+/// 1970-01-01.
+fn parse_date(date: &str) -> Option<i64> {
+    let parts: Vec<&str> = date.trim().split(['.', '/', '-']).collect();
+    let [first, month, last] = parts.as_slice() else {
+        return None;
+    };
+    let (year, day) = if first.len() == 4 {
+        (first, last)
+    } else {
+        (last, first)
+    };
+
+    let year: i64 = year.parse().ok()?;
+    let month: i64 = month.parse().ok()?;
+    let day: i64 = day.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    // Days from civil date, counting years from March so leap days come last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+/// The number of days in a month, counting leap years.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Today's date in days since 1970-01-01 (UTC).
+fn current_day() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|elapsed| i64::try_from(elapsed.as_secs() / 86_400).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("01.01.1970", 0; "epoch")]
+    #[test_case("1970-01-02", 1; "iso")]
+    #[test_case("29/02/2024", 19_782; "leap day")]
+    #[test_case("2000-02-29", 11_016; "leap day in century year")]
+    #[test_case("01.03.2024", 19_783; "after leap day")]
+    #[test_case("31.12.1969", -1; "before epoch")]
+    fn parses_dates(date: &str, days: i64) {
+        assert_eq!(parse_date(date), Some(days));
+    }
+
+    #[test_case("soon"; "text")]
+    #[test_case("2024-13-01"; "month out of range")]
+    #[test_case("32.01.2024"; "day out of range")]
+    #[test_case("2024-02-31"; "day past end of month")]
+    #[test_case("31.04.2024"; "day past end of short month")]
+    #[test_case("29.02.2023"; "leap day in common year")]
+    #[test_case("29.02.1900"; "leap day in century year")]
+    #[test_case("01.2024"; "missing day")]
+    fn rejects_invalid_dates(date: &str) {
+        assert_eq!(parse_date(date), None);
     }
 }
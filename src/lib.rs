@@ -2,33 +2,21 @@
 //!
 //! Templates are provided with multiple context variables:
 //!
-//! - `scale`: a float representing the recipe scaling factor (i.e. 1 by default)
+//! - `scale`: a float representing the recipe scaling factor (i.e. 1 by default). When scaling to
+//!   target servings or yield, this is the factor that was applied.
 //! - `sections`: the sections, containing steps and text, within the recipe
 //! - `ingredients`: the list of ingredients in the recipe
 //! - `cookware`: the list of cookware pieces in the recipe
 //! - `metadata`: the dictionary of metadata from the recipe
 //!
+//! When rendering several recipes at once with [`render_collection`], the recipe variables are
+//! replaced by `recipes`, a list whose entries each have a `name` plus the `scale`, `sections`,
+//! `ingredients`, `cookware` and `metadata` above.
+//!
 //! For more details about each of these, look through the source for the `models` module`.`
 //!
 //! [00]: https://cooklang.org/
 //! [01]: https://jinja.palletsprojects.com/en/stable/
-#[doc = include_str!("../README.md")]
-use cooklang::Recipe;
-use filters::{
-    camelize_filter, dasherize_filter, format_price_filter, humanize_filter, numeric_filter,
-    titleize_filter, underscore_filter, upcase_first_filter,
-};
-use functions::{
-    aisled, excluding_pantry, from_pantry, get_from_datastore, get_ingredient_list,
-    number_to_currency, number_to_human, number_to_human_size, number_to_percentage,
-    number_with_delimiter, number_with_precision,
-};
-use minijinja::Environment;
-use model::{Cookware, Ingredient, Metadata, Section};
-use parser::{get_converter, get_parser};
-use serde::Serialize;
-use yaml_datastore::Datastore;
-
 pub mod config;
 pub mod error;
 pub mod extension;
@@ -36,6 +24,9 @@ mod filters;
 mod functions;
 mod model;
 pub mod parser;
+pub mod renderer;
+pub mod rounding;
+pub mod warning;
 
 pub use config::Config;
 pub use error::Error;
@@ -44,56 +35,13 @@ pub use extension::ConfigExtension;
 /// can reference [`minijinja::Environment`] without pinning a separate
 /// (potentially incompatible) version of the crate.
 pub use minijinja;
+pub use renderer::{RenderOutput, Renderer};
+pub use rounding::RoundingRule;
+pub use warning::{Warning, WarningKind};
 
-/// Context passed to the template
-#[derive(Debug, Serialize)]
-struct TemplateContext {
-    scale: f64,
-    datastore: Option<Datastore>,
-    base_path: Option<String>,
-    aisle_content: Option<String>,
-    pantry_content: Option<String>,
-    sections: Vec<minijinja::Value>,
-    ingredients: Vec<minijinja::Value>,
-    cookware: Vec<minijinja::Value>,
-    metadata: minijinja::Value,
-}
-
-impl TemplateContext {
-    fn new(
-        recipe: Recipe,
-        scale: f64,
-        datastore: Option<Datastore>,
-        base_path: Option<String>,
-        aisle_content: Option<String>,
-        pantry_content: Option<String>,
-    ) -> TemplateContext {
-        TemplateContext {
-            scale,
-            datastore,
-            base_path,
-            aisle_content,
-            pantry_content,
-            sections: Section::from_recipe_sections(&recipe)
-                .into_iter()
-                .map(minijinja::Value::from_object)
-                .collect(),
-            ingredients: recipe
-                .ingredients
-                .into_iter()
-                .map(Ingredient::from)
-                .map(minijinja::Value::from)
-                .collect(),
-            cookware: recipe
-                .cookware
-                .into_iter()
-                .map(Cookware::from)
-                .map(minijinja::Value::from)
-                .collect(),
-            metadata: Metadata::from(recipe.metadata).into(),
-        }
-    }
-}
+#[doc = include_str!("../README.md")]
+#[cfg(doctest)]
+struct ReadmeDoctests;
 
 /// Render a recipe with the deault configuration.
 ///
@@ -113,6 +61,9 @@ pub fn render_template(recipe: &str, template: &str) -> Result<String, Error> {
 ///
 /// On success, returns a String with the recipe as rendered by the template.
 ///
+/// This builds a fresh [`Renderer`] on every call. When rendering many recipes with the
+/// same template, build a [`Renderer`] once and reuse it instead.
+///
 /// # Parameters
 ///
 /// * `recipe` is a (hopefully valid) cooklang recipe as a string, ready to be parsed.
@@ -130,156 +81,38 @@ pub fn render_template_with_config(
     template: &str,
     config: &Config,
 ) -> Result<String, Error> {
-    // Parse and validate recipe string using global parser
-    let (mut recipe, warnings) = get_parser().parse(recipe).into_result()?;
-
-    // Log warnings if present
-    if warnings.has_warnings() {
-        for warning in warnings.warnings() {
-            eprintln!("Warning: {warning}");
-        }
-    }
-
-    // Scale the recipe using global converter
-    recipe.scale(config.scale, get_converter());
-    let datastore = config.datastore_path.as_ref().map(Datastore::open);
-    let base_path = config
-        .base_path
-        .as_ref()
-        .and_then(|p| p.to_str())
-        .map(String::from);
-
-    // Load aisle configuration content if provided
-    let aisle_content = if let Some(aisle_path) = &config.aisle_path {
-        match std::fs::read_to_string(aisle_path) {
-            Ok(content) => {
-                // Validate the aisle file
-                let result = cooklang::aisle::parse_lenient(&content);
-
-                // Log warnings if present
-                if result.report().has_warnings() {
-                    for warning in result.report().warnings() {
-                        eprintln!("Warning in aisle file: {warning}");
-                    }
-                }
-
-                Some(content)
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to read aisle file: {e}");
-                None
-            }
-        }
-    } else {
-        None
-    };
-
-    // Load pantry configuration content if provided
-    let pantry_content = if let Some(pantry_path) = &config.pantry_path {
-        match std::fs::read_to_string(pantry_path) {
-            Ok(content) => {
-                // Validate the pantry file
-                let result = cooklang::pantry::parse_lenient(&content);
-
-                // Log warnings if present
-                if result.report().has_warnings() {
-                    for warning in result.report().warnings() {
-                        eprintln!("Warning in pantry file: {warning}");
-                    }
-                }
-
-                Some(content)
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to read pantry file: {e}");
-                None
-            }
-        }
-    } else {
-        None
-    };
-
-    let template_context = TemplateContext::new(
-        recipe,
-        config.scale,
-        datastore,
-        base_path,
-        aisle_content,
-        pantry_content,
-    );
-    let template_environment = template_environment(template, config)?;
-    let template: minijinja::Template<'_, '_> = template_environment.get_template("base")?;
-
-    // Build the render context: start with the standard TemplateContext, then
-    // overlay any extras from Config::with_context. Extras win on conflict.
-    if config.extra_context.is_empty() {
-        Ok(template.render(template_context)?)
-    } else {
-        let base = minijinja::Value::from_serialize(&template_context);
-        let extras = minijinja::Value::from_serialize(&config.extra_context);
-        let merged = minijinja::value::merge_maps([base, extras]);
-        Ok(template.render(merged)?)
-    }
+    Renderer::new(template, config)?.render(recipe)
 }
 
-/// Build an environment for the given template, registering built-in and extension functions.
-fn template_environment<'a>(
-    template: &'a str,
-    config: &'a Config,
-) -> Result<Environment<'a>, Error> {
-    let mut env = Environment::new();
-
-    // Enable debug mode for better error messages
-    env.set_debug(true);
-
-    env.add_template("base", template)?;
-    env.add_function("db", get_from_datastore);
-    env.add_function("get_ingredient_list", get_ingredient_list);
-    env.add_function("aisled", aisled);
-    env.add_function("excluding_pantry", excluding_pantry);
-    env.add_function("from_pantry", from_pantry);
-
-    // Number formatting functions (also available as filters)
-    env.add_function("number_to_currency", number_to_currency);
-    env.add_function("number_to_human", number_to_human);
-    env.add_function("number_to_human_size", number_to_human_size);
-    env.add_function("number_to_percentage", number_to_percentage);
-    env.add_function("number_with_delimiter", number_with_delimiter);
-    env.add_function("number_with_precision", number_with_precision);
-
-    // Also register as filters
-    env.add_filter("number_to_currency", number_to_currency);
-    env.add_filter("number_to_human", number_to_human);
-    env.add_filter("number_to_human_size", number_to_human_size);
-    env.add_filter("number_to_percentage", number_to_percentage);
-    env.add_filter("number_with_delimiter", number_with_delimiter);
-    env.add_filter("number_with_precision", number_with_precision);
-
-    env.add_filter("numeric", numeric_filter);
-    env.add_filter("format_price", format_price_filter);
-
-    // String transformation filters (also available as functions)
-    env.add_filter("camelize", camelize_filter);
-    env.add_filter("underscore", underscore_filter);
-    env.add_filter("dasherize", dasherize_filter);
-    env.add_filter("humanize", humanize_filter);
-    env.add_filter("titleize", titleize_filter);
-    env.add_filter("upcase_first", upcase_first_filter);
-
-    // Also register as functions for direct calls
-    env.add_function("camelize", camelize_filter);
-    env.add_function("underscore", underscore_filter);
-    env.add_function("dasherize", dasherize_filter);
-    env.add_function("humanize", humanize_filter);
-    env.add_function("titleize", titleize_filter);
-    env.add_function("upcase_first", upcase_first_filter);
-
-    // Register any consumer-provided extensions
-    for ext in &config.extensions {
-        ext.register(&mut env);
-    }
-
-    Ok(env)
+/// Render a collection of recipes to a String with the provided [`Config`].
+///
+/// Instead of the single-recipe variables, the template receives a `recipes` list. Each
+/// entry has the recipe's `name` along with the same `scale`, `sections`, `ingredients`, `cookware`
+/// and `metadata` available when rendering a single recipe.
+///
+/// # Parameters
+///
+/// * `recipes` is a slice of `(name, source)` pairs, where `name` is typically the recipe's
+///   name or path and `source` is the cooklang recipe as a string.
+/// * `template` is a (hopefully valid) template.
+/// * `config` is a [`Config`][`config::Config`] with options for rendering the recipes.
+///
+/// # Errors
+///
+/// Returns [`CollectionRecipeParseError`][`Error::CollectionRecipeParseError`] naming the
+/// first recipe that cannot be parsed by the [`CooklangParser`][`cooklang::CooklangParser`].
+///
+/// Returns [`TemplateError`][`Error::TemplateError`] if the template has a syntax error or rendering fails.
+pub fn render_collection<N, S>(
+    recipes: &[(N, S)],
+    template: &str,
+    config: &Config,
+) -> Result<String, Error>
+where
+    N: AsRef<str>,
+    S: AsRef<str>,
+{
+    Renderer::new(template, config)?.render_collection(recipes)
 }
 
 #[cfg(test)]
@@ -287,6 +120,7 @@ mod tests {
     use super::*;
     use indoc::indoc;
     use std::path::PathBuf;
+    use test_case::test_case;
 
     fn get_test_data_path() -> PathBuf {
         let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -342,6 +176,203 @@ mod tests {
         assert!(result.contains(r#"Missing key: "" (should be empty)"#));
     }
 
+    #[test]
+    fn datastore_default_value() {
+        let config = Config::builder()
+            .datastore_path(get_test_data_path().join("db"))
+            .strict(true)
+            .build();
+        let template = "{{ db('eggs.meta.density', default='n/a') }} {{ db('eggs.shopping.brand', default='n/a') }}";
+
+        let result = render_template_with_config("@eggs{2}", template, &config).unwrap();
+        assert_eq!(result, "1.03 n/a");
+    }
+
+    #[test_case("eggs.meta.density" => "yes" ; "value")]
+    #[test_case("eggs.meta" => "yes" ; "file")]
+    #[test_case("eggs" => "no" ; "directory")]
+    #[test_case("eggs.meta.colour" => "no" ; "missing key")]
+    #[test_case("butter.meta" => "no" ; "missing file")]
+    fn datastore_exists(keypath: &str) -> String {
+        let config = Config::builder()
+            .datastore_path(get_test_data_path().join("db"))
+            .build();
+        let template = format!("{{{{ 'yes' if db_exists('{keypath}') else 'no' }}}}");
+        render_template_with_config("@eggs{2}", &template, &config).unwrap()
+    }
+
+    #[test_case("" => "eggs, flour, milk" ; "top level")]
+    #[test_case("eggs" => "meta, shopping" ; "directory")]
+    #[test_case("eggs.meta" => "density, storage" ; "file")]
+    #[test_case("eggs.meta.storage" => "fridge life, shelf life" ; "mapping")]
+    #[test_case("butter" => "" ; "missing")]
+    fn datastore_keys(prefix: &str) -> String {
+        let config = Config::builder()
+            .datastore_path(get_test_data_path().join("db"))
+            .build();
+        let template = format!("{{{{ db_keys('{prefix}') | join(', ') }}}}");
+        render_template_with_config("@eggs{2}", &template, &config).unwrap()
+    }
+
+    #[test]
+    fn datastore_keys_across_layers() {
+        let config = Config::builder()
+            .datastore_paths([
+                get_test_data_path().join("household"),
+                get_test_data_path().join("db"),
+            ])
+            .build();
+        let template = indoc! {"
+            {%- for name in db_keys('') if db_exists(name ~ '.shopping') -%}
+            {{ name }}: {{ db(name ~ '.shopping.price') }}
+            {% endfor %}"};
+
+        let result = render_template_with_config("@eggs{2}", template, &config).unwrap();
+        assert_eq!(result, "eggs: 4.2\nflour: 1.5\nmilk: 1.0\n");
+    }
+
+    #[test]
+    fn datastore_attribute_access() {
+        let config = Config::builder()
+            .datastore_paths([
+                get_test_data_path().join("household"),
+                get_test_data_path().join("db"),
+            ])
+            .build();
+        let template = indoc! {"
+            {{ datastore.eggs.meta.density }} {{ datastore.eggs.meta.storage['shelf life'] }}
+            {{ datastore.eggs.shopping.price }} {{ datastore.flour.shopping.price }}
+            {{ datastore.eggs | join(', ') }}
+            {{ 'missing' if datastore.butter is undefined else 'found' }}"};
+
+        let result = render_template_with_config("@eggs{2}", template, &config).unwrap();
+        assert_eq!(result, "1.03 30\n4.2 1.5\nmeta, shopping\nmissing");
+    }
+
+    #[test]
+    fn strict_datastore_missing_key() {
+        let datastore_path = get_test_data_path().join("db");
+        let template = "Line one\n{{ db('eggs.meta.missing') }}";
+        let config = Config::builder()
+            .datastore_path(&datastore_path)
+            .strict(true)
+            .build();
+
+        let err = render_template_with_config("@eggs{2}", template, &config).unwrap_err();
+        let formatted = err.format_with_source();
+        assert!(formatted.contains("key 'eggs.meta.missing' not found in datastore"));
+        let file = datastore_path.join("eggs").join("meta.yml");
+        assert!(
+            formatted.contains(&format!("(looked in '{}')", file.display())),
+            "{formatted}"
+        );
+        assert!(formatted.contains("2 > {{ db("), "{formatted}");
+    }
+
+    #[test]
+    fn layered_datastores() {
+        let household_path = get_test_data_path().join("household");
+        let shared_path = get_test_data_path().join("db");
+        let template = indoc! {"
+            eggs: {{ db('eggs.shopping.price') }} from {{ db_source('eggs.shopping.price') }}
+            flour: {{ db('flour.shopping.price') }} from {{ db_source('flour.shopping.price') }}
+            missing: {{ 'none' if db_source('eggs.shopping.brand') is none else 'found' }}
+            total: {{ cost(ingredients).total }}"};
+        let config = Config::builder()
+            .datastore_paths([&household_path, &shared_path])
+            .build();
+
+        let result =
+            render_template_with_config("@eggs{5} @flour{1%kg}", template, &config).unwrap();
+        let expected = format!(
+            "eggs: 4.2 from {}\nflour: 1.5 from {}\nmissing: none\ntotal: 3.6",
+            household_path.display(),
+            shared_path.display()
+        );
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn nutrition_from_datastore() {
+        let datastore_path = get_test_data_path().join("db");
+        let recipe_path = get_test_data_path().join("recipes").join("Pancakes.cook");
+        let recipe = std::fs::read_to_string(recipe_path).unwrap();
+
+        let template = indoc! {"
+            {%- set facts = nutrition(ingredients, servings=2) -%}
+            {%- for i in facts.ingredients %}
+            {{ i.name }}: {{ i.grams | round(1) }} g, {{ i.nutrients.calories | round(1) }} kcal
+            {%- endfor %}
+            Total: {{ facts.total.calories | round(2) }} kcal, {{ facts.total.protein | round(2) }} g protein
+            Per serving: {{ facts.per_serving.calories | round(2) }} kcal
+            Unresolved: {{ facts.unresolved | join(', ') }}"};
+
+        let config = Config::builder().datastore_path(&datastore_path).build();
+        let result = render_template_with_config(&recipe, template, &config).unwrap();
+        let expected = indoc! {"
+
+            milk: 257.5 g, 108.2 kcal
+            flour: 125.0 g, 455.0 kcal
+            Total: 563.15 kcal, 21.63 g protein
+            Per serving: 281.58 kcal
+            Unresolved: eggs"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn nutrition_per_serving_from_metadata() {
+        let datastore_path = get_test_data_path().join("db");
+        let recipe = "---\nservings: 4\n---\nMix @flour{1%kg} with @milk{1%cup} and @salt{}.";
+        let template = indoc! {"
+            {%- set facts = nutrition(get_ingredient_list(ingredients)) -%}
+            {{ facts.servings }} servings, {{ facts.per_serving.calories | round(1) }} kcal each
+            Unresolved: {{ facts.unresolved | join(', ') }}"};
+
+        let config = Config::builder().datastore_path(&datastore_path).build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        // 1 kg flour is 3640 kcal, 1 cup (236.6 ml) of milk is about 102.4 kcal
+        let expected = indoc! {"
+            4.0 servings, 935.6 kcal each
+            Unresolved: salt"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn cost_converts_to_priced_unit() {
+        let datastore_path = get_test_data_path().join("db");
+        let recipe = "Mix @flour{250%g}, @milk{1/2%cup}, @eggs{2%large} and @salt{1%tsp}.";
+        let template = indoc! {"
+            {%- set costs = cost(ingredients) -%}
+            {%- for item in costs.ingredients %}
+            {{ item.name }}: {{ item.cost | format_price }}
+            {%- endfor %}
+            Total: {{ costs.total | format_price }}
+            Unpriced: {{ costs.unpriced | join(', ') }}"};
+
+        let config = Config::builder().datastore_path(&datastore_path).build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        // flour is priced per kg, milk per litre and eggs per dozen
+        let expected = indoc! {"
+
+            flour: 0.38
+            milk: 0.12
+            eggs: 0.50
+            Total: 0.99
+            Unpriced: salt"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn cost_of_incompatible_unit_is_unpriced() {
+        let datastore_path = get_test_data_path().join("db");
+        let recipe = "Mix @flour{2%cups} with @eggs{100%g}.";
+        let template = "{{ cost(ingredients).unpriced | join(', ') }}";
+
+        let config = Config::builder().datastore_path(&datastore_path).build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        assert_eq!(result, "flour, eggs");
+    }
+
     #[test]
     fn test_datastore_access() {
         let datastore_path = get_test_data_path().join("db");
@@ -460,6 +491,154 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn practical_rounding_after_scaling() {
+        let recipe = "Mix @eggs{2}, @salt{1%tsp}, @butter{4%tbsp} and @flour{100%g}.";
+        let template = indoc! {"
+            {%- for ingredient in ingredients %}
+            - {{ ingredient.name }}: {{ ingredient.quantity }}
+            {%- endfor %}"};
+        let config = Config::builder()
+            .scale(4.0 / 3.0)
+            .practical_rounding(true)
+            .build();
+
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        let expected = indoc! {"
+
+            - eggs: 3
+            - salt: 1.25 tsp
+            - butter: 5.5 tbsp
+            - flour: 135 g"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn practical_filter() {
+        let recipe = "Mix @eggs{2} and @salt{10%tsp}.";
+        let template = indoc! {"
+            {{ ingredients[0].quantity }} {{ ingredients[0].quantity | practical }}
+            {{ ingredients[1].quantity }} {{ ingredients[1].quantity | practical | kitchen }}"};
+        let config = Config::builder()
+            .scale(4.0 / 3.0)
+            .rounding_rule("", RoundingRule::nearest(0.5))
+            .build();
+
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        assert_eq!(result, "2.667 2.5\n13.333 tsp 4 1/2 tbsp");
+    }
+
+    #[test]
+    fn step_attributes() {
+        let recipe = indoc! {"
+            Whisk @eggs{2} and @milk{100%ml} in a #bowl{} for ~{1%minute}.
+
+            Fry in a #pan{} for ~{3%minutes}, then rest for ~{2%minutes}."};
+        let template = indoc! {"
+            {%- for step in sections[0].content %}
+            {%- set ingredients = step.ingredients | map(attribute='name') | join(', ') %}
+            {%- set cookware = step.cookware | map(attribute='name') | join(', ') %}
+            {{ step.number }}. {{ ingredients }} | {{ cookware }} | {{ step.duration }}: {{ step.text }}
+            {%- endfor %}"};
+
+        let result = render_template_with_config(recipe, template, &Config::default()).unwrap();
+        let expected = indoc! {"
+
+            1. eggs, milk | bowl | 1 minute: Whisk 2 eggs and 100 ml milk in a bowl for 1 minute.
+            2.  | pan | 5 minutes: Fry in a pan for 3 minutes, then rest for 2 minutes."};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn step_items_by_type() {
+        let recipe = "Heat @olive oil{2%tbsp} in a #frying pan{} for ~{2%minutes}.";
+        let template = indoc! {r#"
+            {%- for step in sections[0] %}
+            {%- for item in step %}
+            {%- if item.type == "ingredient" %}<b>{{ item.name }}</b>
+            {%- elif item.type == "cookware" %}<a href="/cookware/{{ item.name }}">{{ item.name }}</a>
+            {%- else %}{{ item }}{% endif %}
+            {%- endfor %}
+            {%- endfor %}"#};
+
+        let result = render_template_with_config(recipe, template, &Config::default()).unwrap();
+        assert_eq!(
+            result,
+            r#"Heat <b>olive oil</b> in a <a href="/cookware/frying pan">frying pan</a> for 2 minutes."#
+        );
+    }
+
+    #[test]
+    fn scale_to_target_servings() {
+        let recipe_path = get_test_data_path()
+            .join("recipes")
+            .join("Recipe With Servings.cook");
+        let recipe = std::fs::read_to_string(recipe_path).unwrap();
+
+        let template: &str = indoc! {"
+            # Serves {{ metadata.servings }} ({{ scale }}x)
+            {%- for ingredient in ingredients %}
+            - {{ ingredient.name }}: {{ ingredient.quantity }}
+            {%- endfor %}
+        "};
+
+        let config = Config::builder().target_servings(6).build();
+        let result = render_template_with_config(&recipe, template, &config).unwrap();
+        let expected = indoc! {"
+            # Serves 6 (1.5x)
+            - flour: 300 g
+            - milk: 450 ml
+            - eggs: 3"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn scale_to_target_yield() {
+        let recipe_path = get_test_data_path()
+            .join("recipes")
+            .join("Recipe With Yield.cook");
+        let recipe = std::fs::read_to_string(recipe_path).unwrap();
+
+        let template: &str = indoc! {"
+            # {{ scale }}x
+            {%- for ingredient in ingredients %}
+            - {{ ingredient.name }}: {{ ingredient.quantity }}
+            {%- endfor %}
+        "};
+
+        let config = Config::builder().target_yield(1000.0, "g").build();
+        let result = render_template_with_config(&recipe, template, &config).unwrap();
+        let expected = indoc! {"
+            # 2.0x
+            - butter: 200 g
+            - sugar: 300 g
+            - flour: 500 g"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn scale_to_target_errors() {
+        use cooklang::scale::ScaleError;
+
+        let config = Config::builder().target_servings(2).build();
+        let err = render_template_with_config("@eggs{2}", "{{ scale }}", &config).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ScaleError(ScaleError::InvalidServings)
+        ));
+
+        let config = Config::builder().target_yield(1.0, "kg").build();
+        let err = render_template_with_config("@eggs{2}", "{{ scale }}", &config).unwrap_err();
+        assert!(matches!(err, Error::ScaleError(ScaleError::InvalidYield)));
+
+        let recipe = "---\nyield: 500%g\n---\n@flour{250%g}";
+        let err = render_template_with_config(recipe, "{{ scale }}", &config).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ScaleError(ScaleError::UnitMismatch { .. })
+        ));
+    }
+
     #[test]
     fn test_with_template_from_files() {
         // Use Pancakes.cook from test data
@@ -942,6 +1121,122 @@ mod tests {
         assert!(result.contains("- flour:"));
     }
 
+    #[test_case("aisle", "dairy: milk, eggs; grains: flour; produce: garlic; other: paprika, chives"; "aisle file order")]
+    #[test_case("alphabetical", "dairy: eggs, milk; grains: flour; produce: garlic; other: chives, paprika"; "alphabetical")]
+    fn aisled_order(sort: &str, expected: &str) {
+        let aisle_path = get_test_data_path().join("aisle.conf");
+        let recipe =
+            "Add @garlic{1}, @paprika{}, @eggs{2}, @flour{100%g}, @chives{} and @milk{1%cup}.";
+        let template = indoc! {"
+            {%- for aisle, items in aisled(ingredients, sort=sort) | items -%}
+            {{ aisle }}: {{ items | map(attribute='name') | join(', ') }}{{ '; ' if not loop.last }}
+            {%- endfor %}"};
+
+        let config = Config::builder()
+            .aisle_path(&aisle_path)
+            .build()
+            .with_context("sort", sort);
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn aisled_merges_synonyms_in_ingredient_list() {
+        let aisle_path = get_test_data_path().join("aisle.conf");
+        let recipe = indoc! {"
+            Chop @scallion{2} and @spring onion{1}.
+            Mix @milk{100%ml}, @flour{1%kg} and more @milk{100%ml}."};
+        let template = indoc! {"
+            {%- for aisle, items in aisled(get_ingredient_list(ingredients)) | items %}
+            {{ aisle }}: {% for item in items %}{{ item.name }} ({{ item.quantities }}){% endfor %}
+            {%- endfor %}"};
+
+        let config = Config::builder().aisle_path(&aisle_path).build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        let expected = indoc! {"
+
+            dairy: milk (200 ml)
+            grains: flour (1 kg)
+            produce: green onion (3)"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn aisled_merges_synonyms_in_different_units() {
+        let aisle_path = get_test_data_path().join("aisle.conf");
+        let recipe = "Chop @scallion{1%cup} and @spring onion{250%ml}.";
+        let template = indoc! {"
+            {%- for aisle, items in aisled(get_ingredient_list(ingredients)) | items %}
+            {{ aisle }}: {% for item in items %}{{ item.name }} ({{ item.quantities }}){% endfor %}
+            {%- endfor %}"};
+
+        let config = Config::builder().aisle_path(&aisle_path).build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        assert_eq!(result, "\nproduce: green onion (2.057 cup)");
+    }
+
+    #[test]
+    fn aisled_matches_synonyms_of_recipe_ingredients() {
+        let aisle_path = get_test_data_path().join("aisle.conf");
+        let recipe = "Slice @Tomatoes{2} and @garlic{1%clove}.";
+        let template = indoc! {"
+            {%- for aisle, items in aisled(ingredients) | items %}
+            {{ aisle }}: {% for item in items %}{{ item.name }} ({{ item.quantity }}) {% endfor %}
+            {%- endfor %}"};
+
+        let config = Config::builder().aisle_path(&aisle_path).build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        let expected = indoc! {"
+
+            produce: garlic (1 clove) tomato (2) "};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn aisled_per_store() {
+        let recipe = "Mix @milk{1%cup}, @eggs{2}, @flour{100%g} and @garlic{1%clove}.";
+        let template = indoc! {"
+            {%- for store in stores %}
+            # {{ store }}
+            {%- for aisle, items in aisled(ingredients, store=store) | items %}
+            {{ aisle }}: {{ items | map(attribute='name') | join(', ') }}
+            {%- endfor %}
+            {%- endfor %}
+            # default
+            {%- for aisle, items in aisled(ingredients) | items %}
+            {{ aisle }}: {{ items | map(attribute='name') | join(', ') }}
+            {%- endfor %}"};
+
+        let config = Config::builder()
+            .aisle_path(get_test_data_path().join("aisle.conf"))
+            .aisle_config("costco", get_test_data_path().join("costco.conf"))
+            .build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        let expected = indoc! {"
+
+            # costco
+            bulk: flour
+            fridge: eggs, milk
+            other: garlic
+            # default
+            dairy: milk, eggs
+            grains: flour
+            produce: garlic"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn aisled_unknown_store() {
+        let template = "{{ aisled(ingredients, store='corner shop') }}";
+        let config = Config::builder()
+            .aisle_config("costco", get_test_data_path().join("costco.conf"))
+            .build();
+
+        let err = render_template_with_config("@eggs{2}", template, &config).unwrap_err();
+        let formatted = err.format_with_source();
+        assert!(formatted.contains("unknown store 'corner shop', configured stores: costco"));
+    }
+
     #[test]
     fn test_aisled_with_template_file() {
         // Use Chinese Udon Noodles which has more ingredients
@@ -1021,6 +1316,87 @@ mod tests {
         assert!(result.contains("- milk:"));
     }
 
+    #[test]
+    fn still_needed_subtracts_pantry_stock() {
+        let pantry_path = get_test_data_path().join("pantry.conf");
+        let recipe = indoc! {"
+            Mix @flour{2500%g}, @salt{1%tsp}, @butter{100%g}, @olive oil{1%cup},
+            @vegetable oil{2%l} and @eggs{2}."};
+        let template = indoc! {"
+            # Need to buy
+            {%- for item in still_needed(get_ingredient_list(ingredients)) %}
+            - {{ item.name }}: {{ item.quantities }}
+            {%- endfor %}"};
+
+        let config = Config::builder().pantry_path(&pantry_path).build();
+        let result = render_template_with_config(recipe, template, &config).unwrap();
+        // 2 kg of flour and 1 l of vegetable oil are in stock; salt can't be compared to
+        // the 1 kg in stock, so it's kept in full
+        let expected = indoc! {"
+            # Need to buy
+            - flour: 500 g
+            - salt: 1 tsp
+            - vegetable oil: 1 l
+            - eggs: 2"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn still_needed_without_pantry_keeps_everything() {
+        let recipe = "Mix @flour{2%kg} with @salt.";
+        let template = "{{ still_needed(ingredients) | join(', ') }}";
+
+        let result = render_template(recipe, template).unwrap();
+        assert_eq!(result, "flour: 2 kg, salt");
+    }
+
+    #[test]
+    fn pantry_expiry_and_low_stock() {
+        let pantry_path = get_test_data_path().join("pantry.conf");
+        let template = indoc! {"
+            # Use soon
+            {%- for item in expiring_pantry(days=5, today='2026-10-16') %}
+            - {{ item.name }} ({{ item.section }}): {{ item.days_left }} days
+            {%- endfor %}
+            # Restock
+            {%- for item in low_stock_pantry() %}
+            - {{ item.name }}: {{ item.quantity }} (low at {{ item.low }})
+            {%- endfor %}
+            {{ pantry.spices[1].quantity | convert('kg') }} {{ pantry.dairy[0].low.value }}"};
+
+        let config = Config::builder().pantry_path(&pantry_path).build();
+        let result = render_template_with_config("", template, &config).unwrap();
+        let expected = indoc! {"
+            # Use soon
+            - yogurt (dairy): -6 days
+            - butter (dairy): 4 days
+            # Restock
+            - pepper: 50 g (low at 0.1 kg)
+            0.05 kg 100"};
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn pantry_variable() {
+        let pantry_path = get_test_data_path().join("pantry.conf");
+        let template = indoc! {"
+            {%- for section, items in pantry | items %}
+            {{ section }}: {{ items | map(attribute='name') | join(', ') }}
+            {%- endfor %}
+            {{ pantry.dairy[0].bought }}"};
+
+        let config = Config::builder().pantry_path(&pantry_path).build();
+        let result = render_template_with_config("", template, &config).unwrap();
+        let expected = indoc! {"
+
+            spices: salt, pepper
+            baking: flour
+            oils: olive oil, vegetable oil
+            dairy: butter, yogurt
+            01.10.2026"};
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn test_from_pantry() {
         // Use Pancakes.cook from test data
@@ -1255,6 +1631,41 @@ mod tests {
         assert_eq!(result, "99.0");
     }
 
+    #[test]
+    fn collection_weekly_menu() {
+        let recipes_path = get_test_data_path().join("recipes");
+        let recipes: Vec<(String, String)> = ["Pancakes", "Recipe With Servings"]
+            .into_iter()
+            .map(|name| {
+                let path = recipes_path.join(format!("{name}.cook"));
+                (name.to_string(), std::fs::read_to_string(path).unwrap())
+            })
+            .collect();
+
+        let template = indoc! {"
+            # Weekly Menu
+            {%- for recipe in recipes %}
+            ## {{ recipe.name }}{% if recipe.metadata.servings %} (serves {{ recipe.metadata.servings }}){% endif %}
+            {%- for ingredient in recipe.ingredients %}
+            - {{ ingredient.name }}: {{ ingredient.quantity }}
+            {%- endfor %}
+            {%- endfor %}
+        "};
+
+        let result = render_collection(&recipes, template, &Config::default()).unwrap();
+        let expected = indoc! {"
+            # Weekly Menu
+            ## Pancakes
+            - eggs: 3 large
+            - milk: 250 ml
+            - flour: 125 g
+            ## Recipe With Servings (serves 4)
+            - flour: 200 g
+            - milk: 300 ml
+            - eggs: 2"};
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn one_section_with_steps() {
         let recipe = indoc! {"
@@ -0,0 +1,194 @@
+//! Command-line interface for rendering a Cooklang recipe with a report template.
+use clap::Parser;
+use cooklang_reports::{Config, Error, Renderer};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Exit code when an input file cannot be read or the output cannot be written.
+const EXIT_IO: u8 = 1;
+/// Exit code when the recipe cannot be parsed.
+const EXIT_RECIPE: u8 = 3;
+/// Exit code when the template is invalid or fails to render.
+const EXIT_TEMPLATE: u8 = 4;
+/// Exit code when the recipe cannot be scaled to the requested servings or yield.
+const EXIT_SCALE: u8 = 5;
+/// Exit code when, with `--strict`, an aisle or pantry file cannot be read or parsed.
+const EXIT_CONFIG: u8 = 6;
+
+/// Render a Cooklang recipe with a Jinja2-style report template.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Recipe file to render. Reads from stdin if omitted or `-`.
+    recipe: Option<PathBuf>,
+
+    /// Template file. Other templates in its directory can be included or extended.
+    #[arg(short, long)]
+    template: PathBuf,
+
+    /// Scaling factor for the recipe.
+    #[arg(short, long, conflicts_with_all = ["servings", "yield"])]
+    scale: Option<f64>,
+
+    /// Scale the recipe to this many servings, based on its `servings` metadata.
+    #[arg(long, conflicts_with = "yield")]
+    servings: Option<u32>,
+
+    /// Scale the recipe to this yield, e.g. `1%kg`, based on its `yield` metadata.
+    #[arg(id = "yield", long = "yield", value_name = "VALUE%UNIT", value_parser = parse_yield)]
+    yield_: Option<(f64, String)>,
+
+    /// Path to a datastore directory. May be repeated, in which case each key is read from
+    /// the first datastore that has it.
+    #[arg(long)]
+    datastore: Vec<PathBuf>,
+
+    /// Path to an aisle configuration file.
+    #[arg(long)]
+    aisle: Option<PathBuf>,
+
+    /// Aisle configuration file of a named store, as `name=path`, for
+    /// `aisled(ingredients, store=name)`. May be repeated.
+    #[arg(long = "store", value_name = "NAME=PATH", value_parser = parse_store)]
+    stores: Vec<(String, PathBuf)>,
+
+    /// Path to a pantry configuration file.
+    #[arg(long)]
+    pantry: Option<PathBuf>,
+
+    /// Base path for referenced recipes. Defaults to the current directory.
+    #[arg(long)]
+    base_path: Option<PathBuf>,
+
+    /// Extra template variable as `key=value`. Values are parsed as JSON when possible,
+    /// otherwise used as strings. May be repeated.
+    #[arg(long = "context", value_name = "KEY=VALUE", value_parser = parse_context)]
+    context: Vec<(String, serde_json::Value)>,
+
+    /// Round scaled quantities to practical sizes, like whole eggs or the nearest 1/4 tsp.
+    #[arg(long)]
+    practical_rounding: bool,
+
+    /// Fail on missing datastore keys, undefined variables and problems with the aisle or
+    /// pantry files instead of warning.
+    #[arg(long)]
+    strict: bool,
+
+    /// Write the report to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn parse_context(arg: &str) -> Result<(String, serde_json::Value), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{arg}'"))?;
+    let value = serde_json::from_str(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+    Ok((key.to_string(), value))
+}
+
+fn parse_store(arg: &str) -> Result<(String, PathBuf), String> {
+    arg.split_once('=')
+        .map(|(name, path)| (name.to_string(), PathBuf::from(path)))
+        .ok_or_else(|| format!("expected NAME=PATH, got '{arg}'"))
+}
+
+fn parse_yield(arg: &str) -> Result<(f64, String), String> {
+    arg.split_once('%')
+        .and_then(|(value, unit)| Some((value.parse().ok()?, unit.to_string())))
+        .ok_or_else(|| format!("expected VALUE%UNIT, e.g. 1%kg, got '{arg}'"))
+}
+
+fn read_input(path: Option<&Path>) -> std::io::Result<String> {
+    match path {
+        Some(path) if path != Path::new("-") => std::fs::read_to_string(path),
+        _ => std::io::read_to_string(std::io::stdin()),
+    }
+}
+
+fn exit_code(err: &Error) -> u8 {
+    match err {
+        Error::RecipeParseError(_) | Error::CollectionRecipeParseError { .. } => EXIT_RECIPE,
+        Error::ScaleError(_) | Error::CollectionScaleError { .. } => EXIT_SCALE,
+        Error::TemplateError(_) => EXIT_TEMPLATE,
+        Error::ConfigError(_) => EXIT_CONFIG,
+        // Errors added to the library later fail with the generic exit code
+        _ => EXIT_IO,
+    }
+}
+
+fn run(args: Args) -> Result<(), ExitCode> {
+    let template = std::fs::read_to_string(&args.template).map_err(|e| {
+        eprintln!(
+            "Error: could not read template {}: {e}",
+            args.template.display()
+        );
+        ExitCode::from(EXIT_IO)
+    })?;
+    let recipe = read_input(args.recipe.as_deref()).map_err(|e| {
+        eprintln!("Error: could not read recipe: {e}");
+        ExitCode::from(EXIT_IO)
+    })?;
+
+    let mut builder = Config::builder();
+    if let Some(scale) = args.scale {
+        builder.scale(scale);
+    }
+    if let Some(servings) = args.servings {
+        builder.target_servings(servings);
+    }
+    if let Some((value, unit)) = args.yield_ {
+        builder.target_yield(value, unit);
+    }
+    if let Some(dir) = args.template.parent() {
+        builder.template_dir(dir);
+    }
+    if !args.datastore.is_empty() {
+        builder.datastore_paths(args.datastore);
+    }
+    if let Some(path) = args.aisle {
+        builder.aisle_path(path);
+    }
+    builder.strict(args.strict);
+    builder.practical_rounding(args.practical_rounding);
+    for (store, path) in args.stores {
+        builder.aisle_config(store, path);
+    }
+    if let Some(path) = args.pantry {
+        builder.pantry_path(path);
+    }
+    if let Some(path) = args.base_path {
+        builder.base_path(path);
+    }
+    let config = args
+        .context
+        .into_iter()
+        .fold(builder.build(), |config, (key, value)| {
+            config.with_context(key, value)
+        });
+
+    let report = Renderer::new(&template, &config)
+        .and_then(|renderer| renderer.render(&recipe))
+        .map_err(|e| {
+            eprintln!("{}", e.format_with_source());
+            ExitCode::from(exit_code(&e))
+        })?;
+
+    if let Some(path) = args.output {
+        std::fs::write(&path, report).map_err(|e| {
+            eprintln!("Error: could not write {}: {e}", path.display());
+            ExitCode::from(EXIT_IO)
+        })
+    } else {
+        print!("{report}");
+        Ok(())
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(code) => code,
+    }
+}
@@ -1,6 +1,7 @@
 use super::Step;
 use serde::Serialize;
 use std::fmt::Display;
+use std::sync::Arc;
 
 /// Wrapper for [`cooklang::Content`] for reporting.
 ///
@@ -15,9 +16,13 @@ use std::fmt::Display;
 /// ```
 ///
 /// For the above, it is formatted according to its `Display` implementation, which renders its [`Step`][`super::Step`] or string.
+///
+/// A step's content also has the step's attributes, like `content.number`, and can be iterated
+/// over for its items.
 #[derive(Clone, Debug, Serialize)]
 pub enum Content {
-    Step(Step),
+    /// Shared, so templates can use the step's attributes without copying it.
+    Step(Arc<Step>),
     Text(String),
 }
 
@@ -33,7 +38,9 @@ impl Content {
         content: cooklang::Content,
     ) -> Self {
         match content {
-            cooklang::Content::Step(step) => Self::Step(Step::from_recipe_step(recipe, step)),
+            cooklang::Content::Step(step) => {
+                Self::Step(Arc::new(Step::from_recipe_step(recipe, step)))
+            }
             cooklang::Content::Text(value) => Self::Text(value),
         }
     }
@@ -50,7 +57,24 @@ impl Display for Content {
 
 impl minijinja::value::Object for Content {
     fn repr(self: &std::sync::Arc<Self>) -> minijinja::value::ObjectRepr {
-        minijinja::value::ObjectRepr::Plain
+        match self.as_ref() {
+            Content::Step(_) => minijinja::value::ObjectRepr::Seq,
+            Content::Text(_) => minijinja::value::ObjectRepr::Plain,
+        }
+    }
+
+    fn get_value(self: &std::sync::Arc<Self>, key: &minijinja::Value) -> Option<minijinja::Value> {
+        match self.as_ref() {
+            Content::Step(step) => step.get_value(key),
+            Content::Text(_) => None,
+        }
+    }
+
+    fn enumerate(self: &std::sync::Arc<Self>) -> minijinja::value::Enumerator {
+        match self.as_ref() {
+            Content::Step(step) => step.enumerate(),
+            Content::Text(_) => minijinja::value::Enumerator::NonEnumerable,
+        }
     }
 
     fn render(self: &std::sync::Arc<Self>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -68,6 +92,8 @@ mod tests {
     #[test_case("> This recipe is great!\n\nI am an actual step.", "{{ content }}", "This recipe is great!"; "initial text")]
     #[test_case("I am an actual step.\n\n> This recipe is great!", "{{ content }}", "1. I am an actual step."; "initial basic step")]
     #[test_case("Rinse @potatoes{1%kg} with @water.\n\n> This recipe is great!", "{{ content }}", "1. Rinse 1 kg potatoes with water."; "interesting step")]
+    #[test_case("Rinse @potatoes{1%kg} with @water.", "{{ content.number }}: {{ content | join('|') }}",
+        "1: Rinse |1 kg potatoes| with |water|."; "step attributes")]
     fn content(recipe: &str, template: &str, expected: &str) {
         let (recipe, env) = get_recipe_and_env(recipe, template);
         let first_content = recipe.sections[0].content[0].clone();
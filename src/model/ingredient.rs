@@ -35,6 +35,15 @@ impl From<cooklang::Ingredient> for Ingredient {
     }
 }
 
+impl Ingredient {
+    /// The same ingredient under another name, e.g. the name an aisle file uses for it.
+    pub(crate) fn renamed(&self, name: String) -> Self {
+        let mut ingredient = self.0.clone();
+        ingredient.name = name;
+        Self(ingredient)
+    }
+}
+
 impl From<Ingredient> for minijinja::Value {
     fn from(value: Ingredient) -> Self {
         Self::from_object(value)
@@ -1,3 +1,4 @@
+use super::quantity::parse_quantity;
 use minijinja::Value;
 use serde::Serialize;
 use std::fmt::{self, Display};
@@ -39,6 +40,29 @@ impl GroupedQuantity {
     pub fn is_empty(&self) -> bool {
         self.quantities.is_empty()
     }
+
+    /// Convert back into cooklang quantities, e.g. to do arithmetic or unit conversions
+    pub fn to_cooklang(&self) -> Vec<cooklang::Quantity> {
+        self.quantities
+            .iter()
+            .map(|q| parse_quantity(&q.value, q.unit.clone()))
+            .collect()
+    }
+}
+
+impl From<cooklang::quantity::GroupedQuantity> for GroupedQuantity {
+    fn from(grouped: cooklang::quantity::GroupedQuantity) -> Self {
+        Self::from_quantities(
+            grouped
+                .into_vec()
+                .into_iter()
+                .map(|qty| Quantity {
+                    value: qty.value().to_string(),
+                    unit: qty.unit().map(String::from),
+                })
+                .collect(),
+        )
+    }
 }
 
 impl Display for GroupedQuantity {
@@ -156,18 +180,9 @@ impl IngredientList {
         let mut items = Vec::new();
 
         for (name, grouped_qty) in list {
-            let quantities: Vec<Quantity> = grouped_qty
-                .into_vec()
-                .into_iter()
-                .map(|qty| Quantity {
-                    value: qty.value().to_string(),
-                    unit: qty.unit().map(String::from),
-                })
-                .collect();
-
             items.push(IngredientListItem {
                 name,
-                quantities: GroupedQuantity::from_quantities(quantities),
+                quantities: GroupedQuantity::from(grouped_qty),
             });
         }
 
@@ -1,5 +1,5 @@
 //! Model for item.
-use super::{Cookware, Ingredient, Timer};
+use super::{Cookware, Ingredient, Quantity, Timer};
 use serde::Serialize;
 use std::fmt::Display;
 
@@ -9,15 +9,36 @@ use std::fmt::Display;
 ///
 /// Constructed from [`cooklang::Item`] and can be converted into [`minijinja::Value`].
 ///
-/// This enum can only be used directly, and has no fields. Its rendering is handled differently
-/// depending on its type.
+/// Its rendering is handled differently depending on its type, which `item.type` gives as
+/// `"text"`, `"ingredient"`, `"cookware"`, `"timer"` or `"inline_quantity"`. Ingredients,
+/// cookware and timers expose the attributes of [`Ingredient`], [`Cookware`] and [`Timer`],
+/// and text items expose their text as `item.value`, so a template can render each kind of
+/// item its own way:
+///
+/// ```text
+/// {% for item in step %}
+/// {%- if item.type == "ingredient" %}<b>{{ item.name }}</b>
+/// {%- elif item.type == "cookware" %}<a href="/cookware/{{ item.name }}">{{ item.name }}</a>
+/// {%- else %}{{ item }}{% endif %}
+/// {%- endfor %}
+/// ```
+///
+/// Inline quantities (for example "bake at 180 °C") are only produced when the parser has the
+/// inline quantities extension enabled. They render like a [`Quantity`], and also expose its
+/// attributes:
+///
+/// ```text
+/// {{ item }}
+/// {{ item.value }}
+/// {{ item.unit }}
+/// ```
 #[derive(Clone, Debug, Serialize)]
 pub enum Item {
     Text(String),
     Ingredient(Ingredient),
     Cookware(Cookware),
     Timer(Timer),
-    //InlineQuantity, // TODO; probably won't implement
+    InlineQuantity(Quantity),
 }
 
 impl From<Item> for minijinja::Value {
@@ -39,7 +60,22 @@ impl Item {
             cooklang::Item::Timer { index } => {
                 Self::Timer(Timer::from(recipe.timers[index].clone()))
             }
-            cooklang::Item::InlineQuantity { index: _ } => unimplemented!(),
+            cooklang::Item::InlineQuantity { index } => {
+                Self::InlineQuantity(Quantity::from(recipe.inline_quantities[index].clone()))
+            }
+        }
+    }
+}
+
+impl Item {
+    /// The item's type, as given by `item.type` in templates.
+    fn kind(&self) -> &'static str {
+        match self {
+            Item::Text(_) => "text",
+            Item::Ingredient(_) => "ingredient",
+            Item::Cookware(_) => "cookware",
+            Item::Timer(_) => "timer",
+            Item::InlineQuantity(_) => "inline_quantity",
         }
     }
 }
@@ -57,6 +93,7 @@ impl Display for Item {
             Item::Timer(timer) => {
                 write!(f, "{}", minijinja::Value::from(timer.clone()))
             }
+            Item::InlineQuantity(quantity) => write!(f, "{quantity}"),
         }
     }
 }
@@ -66,6 +103,27 @@ impl minijinja::value::Object for Item {
         minijinja::value::ObjectRepr::Plain
     }
 
+    fn get_value(self: &std::sync::Arc<Self>, key: &minijinja::Value) -> Option<minijinja::Value> {
+        let key = key.as_str()?;
+        if key == "type" {
+            return Some(minijinja::Value::from(self.kind()));
+        }
+
+        let inner = match self.as_ref() {
+            Item::Text(text) => {
+                return (key == "value").then(|| minijinja::Value::from(text.as_str()));
+            }
+            Item::Ingredient(ingredient) => minijinja::Value::from(ingredient.clone()),
+            Item::Cookware(cookware) => minijinja::Value::from(cookware.clone()),
+            Item::Timer(timer) => minijinja::Value::from(timer.clone()),
+            Item::InlineQuantity(quantity) => minijinja::Value::from(quantity.clone()),
+        };
+        inner
+            .get_attr(key)
+            .ok()
+            .filter(|value| !value.is_undefined())
+    }
+
     fn render(self: &std::sync::Arc<Self>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
     where
         Self: Sized + 'static,
@@ -85,6 +143,13 @@ mod tests {
     #[test_case("@olive oil{} into #frying pan{}.", "{{ item }}", "olive oil"; "ingredient")]
     #[test_case("#frying pan{}.", "{{ item }}", "frying pan"; "cookware")]
     #[test_case("Cook for ~{10%minutes}.", "{{ item }}", "Cook for "; "text before timer")]
+    #[test_case("Measure @olive oil{}.", "{{ item.type }}: {{ item.value }}", "text: Measure "; "text type")]
+    #[test_case("@olive oil{2%tbsp}.", "{{ item.type }}: {{ item.name }}, {{ item.quantity }}",
+        "ingredient: olive oil, 2 tbsp"; "ingredient attributes")]
+    #[test_case("#frying pan{}.", "{{ item.type }}: {{ item.name }}", "cookware: frying pan"; "cookware attributes")]
+    #[test_case("~eggs{10%minutes}.", "{{ item.type }}: {{ item.name }}, {{ item.quantity.unit }}",
+        "timer: eggs, minutes"; "timer attributes")]
+    #[test_case("@olive oil{}.", "{{ 'undefined' if item.value is undefined }}", "undefined"; "unknown attribute")]
     fn item(recipe: &str, template: &str, expected: &str) {
         let (recipe, env) = get_recipe_and_env(recipe, template);
 
@@ -121,4 +186,32 @@ mod tests {
         let template = env.get_template("test").unwrap();
         assert_eq!("10 minutes", template.render(context).unwrap());
     }
+
+    #[test_case("Bake at 180 °C for 20 minutes until golden.", 1, "{{ item }}", "180 °C"; "inline quantity")]
+    #[test_case("Bake at 180 °C for 20 minutes until golden.", 1, "{{ item.value }}", "180"; "inline quantity value")]
+    #[test_case("Bake at 180 °C for 20 minutes until golden.", 3, "{{ item.unit }}", "minutes"; "inline quantity unit")]
+    #[test_case("Bake at 180 °C for 20 minutes until golden.", 1, "{{ item.type }}", "inline_quantity"; "inline quantity type")]
+    fn inline_quantity_item(recipe: &str, index: usize, template: &str, expected: &str) {
+        let parser = cooklang::CooklangParser::new(
+            cooklang::Extensions::INLINE_QUANTITIES,
+            cooklang::Converter::bundled(),
+        );
+        let (recipe, _warnings) = parser.parse(recipe).into_result().unwrap();
+        let (_, env) = get_recipe_and_env("", template);
+
+        let item = match &recipe.sections[0].content[0] {
+            cooklang::Content::Step(step) => {
+                Item::from_recipe_item(&recipe, step.items[index].clone())
+            }
+            cooklang::Content::Text(_) => unreachable!(),
+        };
+
+        // Build context
+        let context = context! {
+            item => Value::from(item)
+        };
+
+        let template = env.get_template("test").unwrap();
+        assert_eq!(expected, template.render(context).unwrap());
+    }
 }
@@ -14,10 +14,13 @@ pub(crate) use content::Content;
 pub(crate) use content_list::ContentList;
 pub(crate) use cookware::Cookware;
 pub(crate) use ingredient::Ingredient;
-pub(crate) use ingredient_list::IngredientList;
+pub(crate) use ingredient_list::{GroupedQuantity, IngredientList, IngredientListItem};
 pub(crate) use item::Item;
 pub(crate) use metadata::Metadata;
-pub(crate) use quantity::{Quantity, quantity_from_value};
+pub(crate) use quantity::{
+    Quantity, ingredient_quantities, parse_quantity, parse_text_value, quantity_from_value,
+    quantity_number,
+};
 pub(crate) use section::Section;
 pub(crate) use step::Step;
 pub(crate) use timer::Timer;
@@ -1,4 +1,9 @@
-use cooklang::quantity::{Quantity as CooklangQuantity, Value as QuantityValue};
+use super::IngredientListItem;
+use crate::filters::fraction::is_simple_fraction;
+use crate::parser::get_unit_converter;
+use cooklang::quantity::{Number, Quantity as CooklangQuantity, Value as QuantityValue};
+use minijinja::value::from_args;
+use minijinja::{Error, ErrorKind::InvalidOperation, State};
 use serde::Serialize;
 use std::fmt::Display;
 
@@ -15,13 +20,27 @@ use std::fmt::Display;
 /// {{ quantity }}
 /// {{ quantity.value }}
 /// {{ quantity.unit }}
+/// {{ quantity.number }}
+/// {{ quantity.min }} to {{ quantity.max }}
+/// {% if quantity.is_range %}...{% endif %}
+/// {{ quantity.mul(2) }} or {{ quantity | mul(2) }}
+/// {{ quantity.add(other_quantity) }} or {{ quantity | add(other_quantity) }}
 /// ```
 ///
-/// # Limitations
+/// `value` is the value as displayed, e.g. `"1/4"` or `"1-2"`. `number` is its numeric value,
+/// the middle of a range, and `min` and `max` are the ends of a range, or the number itself.
+/// All three are none for text, and whole numbers are integers. `is_range`, `is_fraction`
+/// and `is_text` tell the kind of value. Scaling stores fractions as decimals, so
+/// `is_fraction` is true for any number with a simple fractional part, like `0.25` or
+/// `2 2/3`, however it was written.
 ///
-/// While the quantity's value can be used in a template and passed through the builtin
-/// [`float`][minijinja::filters::float] filter, this only works if the value is a number,
-/// and not a range or text.
+/// # Arithmetic
+///
+/// minijinja has no operator overloading for objects, so `quantity * 2` and `q1 + q2` raise
+/// an error. Use the `mul(factor)` and `add(other)` methods or filters instead, which can be
+/// chained as `quantity | mul(2) | add(other)`. `add` converts `other` to this quantity's
+/// unit when both are known units of the same kind, so `500 g` plus `1 kg` is `1500 g`.
+/// Text values and incompatible units are errors.
 #[derive(Clone, Debug, Serialize)]
 pub struct Quantity(cooklang::Quantity);
 
@@ -31,6 +50,51 @@ impl From<cooklang::Quantity> for Quantity {
     }
 }
 
+impl Quantity {
+    /// Get the wrapped cooklang quantity.
+    pub(crate) fn to_cooklang(&self) -> CooklangQuantity {
+        self.0.clone()
+    }
+
+    /// The quantity with a text value parsed as a number or range where possible, since
+    /// the parser keeps values like `1-2` as text.
+    fn parsed(&self) -> CooklangQuantity {
+        parse_text_value(&self.0)
+    }
+
+    /// Add another quantity, converted to this quantity's unit if needed.
+    pub(crate) fn try_add(&self, other: &CooklangQuantity) -> Result<Self, Error> {
+        self.parsed()
+            .try_add(&parse_text_value(other), get_unit_converter())
+            .map(Self)
+            .map_err(|e| {
+                Error::new(
+                    InvalidOperation,
+                    format!("cannot add {other} to {}: {e}", self.0),
+                )
+            })
+    }
+
+    /// Multiply the quantity by a factor, keeping its unit.
+    pub(crate) fn try_mul(&self, factor: f64) -> Result<Self, Error> {
+        let value = match self.parsed().value() {
+            QuantityValue::Number(n) => QuantityValue::Number((n.value() * factor).into()),
+            QuantityValue::Range { start, end } => QuantityValue::Range {
+                start: (start.value() * factor).into(),
+                end: (end.value() * factor).into(),
+            },
+            QuantityValue::Text(_) => {
+                return Err(Error::new(
+                    InvalidOperation,
+                    format!("cannot multiply the text quantity '{}'", self.0),
+                ));
+            }
+        };
+        let unit = self.0.unit().map(String::from);
+        Ok(Self(CooklangQuantity::new(value, unit)))
+    }
+}
+
 impl From<Quantity> for minijinja::Value {
     fn from(value: Quantity) -> Self {
         Self::from_object(value)
@@ -38,12 +102,20 @@ impl From<Quantity> for minijinja::Value {
 }
 
 /// Convert a minijinja Value to a cooklang Quantity
-/// The value should be an object with .value and .unit attributes
+/// The value should be a [`Quantity`] or an object with .value and .unit attributes
 pub fn quantity_from_value(qty_val: &minijinja::Value) -> Result<CooklangQuantity, String> {
+    // Model quantities are used as they are, keeping fractions and ranges intact
+    if let Some(quantity) = qty_val.downcast_object_ref::<Quantity>() {
+        return Ok(quantity.0.clone());
+    }
+
     // Get value and unit from the quantity object
     let value_val = qty_val
         .get_attr("value")
         .map_err(|e| format!("Failed to get quantity value: {e}"))?;
+    if value_val.is_undefined() {
+        return Err(format!("'{qty_val}' is not a quantity"));
+    }
     let value_str = value_val
         .as_str()
         .map_or_else(|| value_val.to_string(), String::from);
@@ -52,36 +124,99 @@ pub fn quantity_from_value(qty_val: &minijinja::Value) -> Result<CooklangQuantit
         .ok()
         .and_then(|u| u.as_str().map(String::from));
 
-    // Parse the value string
-    if let Ok(num) = value_str.parse::<f64>() {
-        // Simple number
-        Ok(CooklangQuantity::new(
-            QuantityValue::Number(num.into()),
-            unit,
-        ))
-    } else if value_str.contains('-') {
+    Ok(parse_quantity(&value_str, unit))
+}
+
+/// Get the quantities of an ingredient: the `quantity` of a recipe ingredient, or the
+/// `quantities` of an item from `get_ingredient_list`. Ingredients without a quantity have none.
+pub(crate) fn ingredient_quantities(ingredient: &minijinja::Value) -> Vec<CooklangQuantity> {
+    if let Some(item) = ingredient.downcast_object_ref::<IngredientListItem>() {
+        return item.quantities.to_cooklang();
+    }
+    ingredient
+        .get_attr("quantity")
+        .ok()
+        .filter(|q| !q.is_none() && !q.is_undefined())
+        .and_then(|q| quantity_from_value(&q).ok())
+        .into_iter()
+        .collect()
+}
+
+/// Get the single number of a quantity value, using the middle of a range.
+/// Text values have no number.
+pub(crate) fn quantity_number(value: &QuantityValue) -> Option<f64> {
+    match value {
+        QuantityValue::Number(n) => Some(n.value()),
+        QuantityValue::Range { start, end } => Some(f64::midpoint(start.value(), end.value())),
+        QuantityValue::Text(_) => None,
+    }
+}
+
+/// Parse a quantity's text value as a number or range where possible.
+pub(crate) fn parse_text_value(quantity: &CooklangQuantity) -> CooklangQuantity {
+    match quantity.value() {
+        QuantityValue::Text(text) => parse_quantity(text, quantity.unit().map(String::from)),
+        _ => quantity.clone(),
+    }
+}
+
+/// A number as a template value, an integer when it is whole so `2.0` renders as `2`.
+#[allow(clippy::cast_possible_truncation)]
+fn number_value(number: f64) -> minijinja::Value {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        minijinja::Value::from(number as i64)
+    } else {
+        minijinja::Value::from(number)
+    }
+}
+
+/// Get the lowest and highest number of a quantity value, the same for a single number.
+/// Text values have no numbers.
+fn quantity_bounds(value: &QuantityValue) -> Option<(f64, f64)> {
+    match value {
+        QuantityValue::Number(n) => Some((n.value(), n.value())),
+        QuantityValue::Range { start, end } => Some((start.value(), end.value())),
+        QuantityValue::Text(_) => None,
+    }
+}
+
+/// Parse a quantity from its displayed value, e.g. `"2"`, `"1 1/2"` or `"1-2"`.
+/// Anything that isn't a number or a range of numbers is kept as text.
+pub(crate) fn parse_quantity(value_str: &str, unit: Option<String>) -> CooklangQuantity {
+    let value = if let Some(num) = parse_number(value_str) {
+        // Simple number or fraction
+        QuantityValue::Number(num)
+    } else if let Some((start, end)) = value_str.split_once('-')
+        && let (Some(start), Some(end)) = (parse_number(start), parse_number(end))
+    {
         // Handle range like "1-2"
-        let parts: Vec<&str> = value_str.split('-').collect();
-        if parts.len() == 2
-            && let (Ok(start), Ok(end)) = (
-                parts[0].trim().parse::<f64>(),
-                parts[1].trim().parse::<f64>(),
-            )
-        {
-            return Ok(CooklangQuantity::new(
-                QuantityValue::Range {
-                    start: start.into(),
-                    end: end.into(),
-                },
-                unit,
-            ));
-        }
-        // If range parsing fails, treat as text
-        Ok(CooklangQuantity::new(QuantityValue::Text(value_str), unit))
+        QuantityValue::Range { start, end }
     } else {
         // Text value
-        Ok(CooklangQuantity::new(QuantityValue::Text(value_str), unit))
+        QuantityValue::Text(value_str.to_string())
+    };
+    CooklangQuantity::new(value, unit)
+}
+
+/// Parse a number as displayed by cooklang: a decimal, `"1/2"` or `"1 1/2"`.
+fn parse_number(value: &str) -> Option<Number> {
+    let value = value.trim();
+    if let Ok(num) = value.parse::<f64>() {
+        return Some(num.into());
     }
+
+    let (whole, fraction) = match value.split_once(' ') {
+        Some((whole, fraction)) => (whole.parse::<u32>().ok()?, fraction.trim()),
+        None => (0, value),
+    };
+    let (num, den) = fraction.split_once('/')?;
+    let (num, den) = (num.parse::<u32>().ok()?, den.parse::<u32>().ok()?);
+    (den != 0).then_some(Number::Fraction {
+        whole,
+        num,
+        den,
+        err: 0.0,
+    })
 }
 
 impl Display for Quantity {
@@ -99,10 +234,49 @@ impl minijinja::value::Object for Quantity {
         match key.as_str()? {
             "value" => Some(minijinja::Value::from(self.0.value().to_string())),
             "unit" => self.0.unit().map(minijinja::Value::from),
+            "number" => Some(minijinja::Value::from(
+                quantity_number(self.parsed().value()).map(number_value),
+            )),
+            "min" => Some(minijinja::Value::from(
+                quantity_bounds(self.parsed().value()).map(|(min, _)| number_value(min)),
+            )),
+            "max" => Some(minijinja::Value::from(
+                quantity_bounds(self.parsed().value()).map(|(_, max)| number_value(max)),
+            )),
+            "is_range" => Some(minijinja::Value::from(matches!(
+                self.parsed().value(),
+                QuantityValue::Range { .. }
+            ))),
+            "is_fraction" => Some(minijinja::Value::from(matches!(
+                self.parsed().value(),
+                QuantityValue::Number(n) if is_simple_fraction(n.value())
+            ))),
+            "is_text" => Some(minijinja::Value::from(self.parsed().value().is_text())),
             _ => None,
         }
     }
 
+    fn call_method(
+        self: &std::sync::Arc<Self>,
+        _state: &State,
+        method: &str,
+        args: &[minijinja::Value],
+    ) -> Result<minijinja::Value, Error> {
+        match method {
+            "add" => {
+                let (other,): (minijinja::Value,) = from_args(args)?;
+                let other =
+                    quantity_from_value(&other).map_err(|e| Error::new(InvalidOperation, e))?;
+                Ok(self.try_add(&other)?.into())
+            }
+            "mul" => {
+                let (factor,): (f64,) = from_args(args)?;
+                Ok(self.try_mul(factor)?.into())
+            }
+            _ => Err(Error::from(minijinja::ErrorKind::UnknownMethod)),
+        }
+    }
+
     fn render(self: &std::sync::Arc<Self>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
     where
         Self: Sized + 'static,
@@ -118,6 +292,11 @@ mod tests {
     use minijinja::{Value, context};
     use test_case::test_case;
 
+    /// Render the kind of a quantity, without relying on how booleans are rendered.
+    const KIND: &str = "{% if quantity.is_fraction %}fraction{% endif %}\
+        {%- if quantity.is_range %}range{% endif %}\
+        {%- if quantity.is_text %}text{% endif %}";
+
     #[test_case("Crack @egg{1} into pan.", "{{ quantity }}", "1"; "number without unit")]
     #[test_case("Pour @flour{100%g} into bowl.", "{{ quantity }}", "100 g"; "number with unit")]
     #[test_case("Crack @eggs{1-2} into pan.", "{{ quantity }}", "1-2"; "range without unit")]
@@ -130,7 +309,22 @@ mod tests {
     #[test_case("Peel @garlic{some%g}.", "{{ quantity.value }}", "some"; "value direct when text")]
     #[test_case("Peel @garlic{1-2%g}.", "{{ quantity.value }}", "1-2"; "value direct when range")]
     #[test_case("Peel @garlic{1%g}.", "{{ quantity.value | float }}", "1.0"; "number value as float")]
-
+    #[test_case("Add @sugar{1/4%cup}.", "{{ quantity.number }}", "0.25"; "number of fraction")]
+    #[test_case("Add @sugar{1 1/2%cup}.", "{{ quantity.number }}", "1.5"; "number of mixed fraction")]
+    #[test_case("Crack @eggs{1-2}.", "{{ quantity.number }}", "1.5"; "number of range")]
+    #[test_case("Peel @garlic{clove}.", "{{ 'none' if quantity.number is none }}", "none"; "number of text")]
+    #[test_case("Crack @eggs{1-2}.", "{{ quantity.min }} {{ quantity.max }}", "1 2"; "range bounds")]
+    #[test_case("Crack @eggs{1.5-2.5}.", "{{ quantity.min }} {{ quantity.max }}", "1.5 2.5"; "range bounds with decimals")]
+    #[test_case("Crack @eggs{3}.", "{{ quantity.min }} {{ quantity.max }}", "3 3"; "number bounds")]
+    #[test_case("Add @sugar{1/4%cup}.", KIND, "fraction"; "number kind")]
+    #[test_case("Add @sugar{2 2/3%cup}.", KIND, "fraction"; "mixed fraction kind")]
+    #[test_case("Crack @eggs{3}.", KIND, ""; "whole number kind")]
+    #[test_case("Pour @milk{0.3%l}.", KIND, ""; "decimal kind")]
+    #[test_case("Crack @eggs{1-2}.", KIND, "range"; "range kind")]
+    #[test_case("Peel @garlic{clove}.", KIND, "text"; "text kind")]
+    #[test_case("Pour @flour{100%g}.", "{{ quantity.mul(2.5) }}", "250 g"; "multiply number")]
+    #[test_case("Crack @eggs{1-2}.", "{{ quantity.mul(2) }}", "2-4"; "multiply range")]
+    #[test_case("Pour @flour{100%g}.", "{{ quantity.mul(2).number * 2 }}", "400"; "multiply then compute")]
     fn quantity(recipe: &str, template: &str, result: &str) {
         let (recipe, env) = get_recipe_and_env(recipe, template);
         let first_quantity_in_recipe = recipe.ingredients[0].quantity.as_ref().unwrap().clone();
@@ -143,4 +337,58 @@ mod tests {
         let template = env.get_template("test").unwrap();
         assert_eq!(result, template.render(context).unwrap());
     }
+
+    #[test]
+    fn fraction_kind() {
+        // Fractions that were not scaled keep their numerator and denominator
+        let quantity = Value::from(Quantity(parse_quantity("1 1/2", Some("cup".into()))));
+        let env = minijinja::Environment::new();
+        let result = env
+            .render_str(
+                &format!("{KIND} {{{{ quantity.number }}}}"),
+                context! { quantity },
+            )
+            .unwrap();
+        assert_eq!(result, "fraction 1.5");
+    }
+
+    #[test_case("Pour @flour{500%g} and @flour{1%kg}.", Ok("1500 g"); "converts to first unit")]
+    #[test_case("Crack @eggs{2} and @eggs{1-2}.", Ok("3-4"); "number and range")]
+    #[test_case("Pour @flour{500%g} and @milk{1%l}.", Err("cannot add 1 l to 500 g"); "incompatible units")]
+    #[test_case("Peel @garlic{clove} and @garlic{1}.", Err("cannot add 1 to clove"); "text")]
+    fn add_quantities(recipe: &str, expected: Result<&str, &str>) {
+        let (recipe, env) = get_recipe_and_env(recipe, "{{ a.add(b) }}");
+        let quantities: Vec<_> = recipe
+            .ingredients
+            .iter()
+            .map(|ingredient| Value::from(Quantity(ingredient.quantity.clone().unwrap())))
+            .collect();
+        let context = context! { a => quantities[0], b => quantities[1] };
+
+        let result = env.get_template("test").unwrap().render(context);
+        match expected {
+            Ok(expected) => assert_eq!(expected, result.unwrap()),
+            Err(expected) => {
+                let err = result.unwrap_err().to_string();
+                assert!(err.contains(expected), "{err}");
+            }
+        }
+    }
+
+    #[test_case("2", "2"; "integer")]
+    #[test_case("1.5", "1.5"; "decimal")]
+    #[test_case("1/2", "1/2"; "fraction")]
+    #[test_case("1 1/2", "1 1/2"; "mixed fraction")]
+    #[test_case("1-2", "1-2"; "range")]
+    #[test_case("1/2-1", "1/2-1"; "range of fractions")]
+    #[test_case("a pinch", "a pinch"; "text")]
+    #[test_case("1/0", "1/0"; "zero denominator is text")]
+    fn parse_quantity_round_trips(value: &str, expected: &str) {
+        let quantity = parse_quantity(value, None);
+        assert_eq!(expected, quantity.value().to_string());
+        assert_eq!(
+            value.contains(char::is_alphabetic) || value == "1/0",
+            matches!(quantity.value(), QuantityValue::Text(_))
+        );
+    }
 }
@@ -12,12 +12,16 @@ use std::fmt::Display;
 /// ```text
 /// {{ section }}
 /// {{ section.name }}
+/// {{ section.content }}
 /// ```
 ///
 /// The usage `{{ section }}` will render the entire section in a human-readable default format.
 ///
 /// `{{ section.name }}` will render the name of the section.
 ///
+/// `{{ section.content }}` is the list of the section's contents, the same as iterating over
+/// the section.
+///
 /// The section may also be iterated over in a template, which will enumerate all its contents. Each
 /// part below is a [`Content`][`super::Content`].
 ///
@@ -72,6 +76,7 @@ impl minijinja::value::Object for Section {
         match key.as_str()? {
             "name" if self.name.is_some() => Some(minijinja::Value::from(self.name.clone())),
             "name" if self.name.is_none() => Some(minijinja::Value::from("")),
+            "content" => Some(minijinja::Value::from(self.content.clone())),
             _ => None,
         }
     }
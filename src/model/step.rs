@@ -1,4 +1,4 @@
-use super::Item;
+use super::{Item, Quantity, parse_text_value};
 use serde::Serialize;
 use std::fmt::Display;
 
@@ -13,12 +13,24 @@ use std::fmt::Display;
 /// ```text
 /// {{ step }}
 /// {{ step.number }}
+/// {{ step.text }}
+/// {% for ingredient in step.ingredients %}{{ ingredient.name }}{% endfor %}
+/// {% for cookware in step.cookware %}{{ cookware.name }}{% endfor %}
+/// {% for timer in step.timers %}{{ timer }}{% endfor %}
+/// {{ step.duration }}
 /// ```
 ///
 /// For the above:
 ///
 /// - `step` formats according to its `Display` implementation, which prints the step number and step text.
 /// - `step.number` renders the step's number.
+/// - `step.text` renders the step's text without its number, with ingredients, cookware and
+///   timers written out as plain text.
+/// - `step.ingredients`, `step.cookware` and `step.timers` list the ingredients, cookware and
+///   timers used in the step, in the order they appear.
+/// - `step.duration` is the total of the step's timers, in the unit of the first one. It is
+///   none if the step has no timers, or if any of them can't be added up, like a timer
+///   without a quantity or with a text one such as `~{a while}`.
 ///
 /// The step may also be iterated over in a template, which will enumerate all its parts. This is for more
 /// advanced processing, for when the `{{ part }}` will need special rendering.
@@ -53,6 +65,33 @@ impl Step {
     }
 }
 
+impl Step {
+    /// The step's text without its number.
+    fn text(&self) -> String {
+        self.items.iter().map(ToString::to_string).collect()
+    }
+
+    /// List the step's items of one kind, like its ingredients.
+    fn items_of(&self, select: impl Fn(&Item) -> Option<minijinja::Value>) -> minijinja::Value {
+        self.items.iter().filter_map(select).collect()
+    }
+
+    /// The total of the step's timers, if every one of them can be added up.
+    fn duration(&self) -> Option<Quantity> {
+        let mut quantities = self.items.iter().filter_map(|item| match item {
+            Item::Timer(timer) => Some(timer.quantity.as_ref()),
+            _ => None,
+        });
+        let first = parse_text_value(&quantities.next()??.to_cooklang());
+        if first.value().is_text() {
+            return None;
+        }
+        quantities.try_fold(Quantity::from(first), |total, quantity| {
+            total.try_add(&quantity?.to_cooklang()).ok()
+        })
+    }
+}
+
 impl minijinja::value::Object for Step {
     fn repr(self: &std::sync::Arc<Self>) -> minijinja::value::ObjectRepr {
         minijinja::value::ObjectRepr::Seq
@@ -66,6 +105,20 @@ impl minijinja::value::Object for Step {
 
         match key.as_str()? {
             "number" => Some(minijinja::Value::from(self.number)),
+            "text" => Some(minijinja::Value::from(self.text())),
+            "ingredients" => Some(self.items_of(|item| match item {
+                Item::Ingredient(ingredient) => Some(ingredient.clone().into()),
+                _ => None,
+            })),
+            "cookware" => Some(self.items_of(|item| match item {
+                Item::Cookware(cookware) => Some(cookware.clone().into()),
+                _ => None,
+            })),
+            "timers" => Some(self.items_of(|item| match item {
+                Item::Timer(timer) => Some(timer.clone().into()),
+                _ => None,
+            })),
+            "duration" => Some(minijinja::Value::from(self.duration())),
             _ => None,
         }
     }
@@ -105,7 +158,19 @@ mod tests {
     #[test_case("Pour @olive oil{}\ninto #frying pan{}", "{{ step.number }}", "1"; "step number")]
     #[test_case("Pour @olive oil{} into #frying pan{}", "{% for part in step %}{{ part }},{% endfor %}",
         "Pour ,olive oil, into ,frying pan,"; "step parts")]
-
+    #[test_case("Pour @olive oil{2%tbsp} into #frying pan{} and fry for ~{5%minutes}.", "{{ step.text }}",
+        "Pour 2 tbsp olive oil into frying pan and fry for 5 minutes."; "step text")]
+    #[test_case("Mix @flour{100%g}, @eggs{2} and @milk{} in a #bowl{}.",
+        "{% for i in step.ingredients %}{{ i.name }} {{ i.quantity }},{% endfor %}", "flour 100 g,eggs 2,milk ,"; "step ingredients")]
+    #[test_case("Mix @flour{} in a #bowl{} with a #whisk{}.",
+        "{{ step.cookware | map(attribute='name') | join(', ') }}", "bowl, whisk"; "step cookware")]
+    #[test_case("Fry for ~{5%minutes}, flip and fry for ~browning{3%minutes}.",
+        "{{ step.timers | join(', ') }}", "5 minutes, browning for 3 minutes"; "step timers")]
+    #[test_case("Fry for ~{5%minutes}, then bake for ~{1%hour}.", "{{ step.duration }}", "65 minutes"; "step duration")]
+    #[test_case("Rest until ~{cool}.", "{{ step.duration or 'none' }}", "none"; "text duration")]
+    #[test_case("Fry for ~{5%min}, then rest for ~{a while}.", "{{ step.duration or 'none' }}", "none"; "text timer in duration")]
+    #[test_case("Fry for ~{5%min}, then rest for ~{1%day}.", "{{ step.duration }}", "1445 min"; "duration in first unit")]
+    #[test_case("Mix @flour{}.", "{{ step.duration or 'none' }} {{ step.timers | length }}", "none 0"; "no timers")]
     fn step(recipe: &str, template: &str, expected: &str) {
         let (recipe, env) = get_recipe_and_env(recipe, template);
 
@@ -11,6 +11,9 @@ use std::sync::OnceLock;
 /// This improves performance by avoiding repeated parser initialization.
 static PARSER: OnceLock<CooklangParser> = OnceLock::new();
 
+/// Global `Converter` with cooklang's bundled units, used for explicit unit conversions.
+static UNIT_CONVERTER: OnceLock<Converter> = OnceLock::new();
+
 /// Get the global `CooklangParser` instance.
 ///
 /// The parser is initialized with all extensions enabled and an empty converter
@@ -37,6 +40,16 @@ pub fn get_converter() -> &'static Converter {
     get_parser().converter()
 }
 
+/// Get a converter that knows cooklang's bundled metric and imperial units.
+///
+/// Unlike [`get_converter`], which is empty so that recipes keep their units exactly as
+/// written, this converter is used where a template explicitly asks for a conversion, such
+/// as the `convert` and `to_system` filters.
+#[must_use]
+pub fn get_unit_converter() -> &'static Converter {
+    UNIT_CONVERTER.get_or_init(Converter::bundled)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,4 +84,11 @@ mod tests {
         let _units: Vec<_> = converter.all_units().collect();
         // If we get here without panic, the test passes
     }
+
+    #[test]
+    fn test_unit_converter_knows_units() {
+        let converter = get_unit_converter();
+        assert!(converter.find_unit("g").is_some());
+        assert!(converter.find_unit("cup").is_some());
+    }
 }
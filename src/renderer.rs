@@ -0,0 +1,963 @@
+//! Reusable renderer that compiles a template once and renders many recipes.
+use crate::config::{Config, Scaling, TemplateSource};
+use crate::error::Error;
+use crate::filters::{
+    add_filter, camelize_filter, convert_filter, dasherize_filter, format_price_filter,
+    fraction_filter, humanize_filter, mul_filter, numeric_filter, titleize_filter,
+    to_system_filter, underscore_filter, upcase_first_filter,
+};
+use crate::functions::aisle::AisleConfig;
+use crate::functions::datastore::LayeredDatastore;
+use crate::functions::pantry::PantryConfig;
+use crate::functions::{
+    aisled, cost, datastore_exists, datastore_keys, datastore_source, excluding_pantry,
+    expiring_pantry, from_pantry, get_from_datastore, get_ingredient_list, low_stock_pantry,
+    number_to_currency, number_to_human, number_to_human_size, number_to_percentage,
+    number_with_delimiter, number_with_precision, nutrition, still_needed,
+};
+use crate::model::{Cookware, Ingredient, Metadata, Section};
+use crate::parser::{get_converter, get_parser};
+use crate::rounding::PracticalRounding;
+use crate::warning::{self, Warning, WarningKind};
+use cooklang::Recipe;
+use cooklang::scale::ScaleError;
+use minijinja::{Environment, UndefinedBehavior, Value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name under which the main template is registered in the environment.
+const BASE_TEMPLATE: &str = "base";
+
+/// Context passed to the template
+#[derive(Debug, Serialize)]
+struct TemplateContext {
+    datastore: Option<Value>,
+    base_path: Option<String>,
+    aisle_config: Option<Value>,
+    store_aisle_configs: BTreeMap<String, Value>,
+    stores: Vec<String>,
+    pantry_config: Option<Value>,
+    pantry: Option<Value>,
+    #[serde(flatten)]
+    content: RenderContent,
+}
+
+/// What is being rendered: a single recipe, whose parts are top-level variables, or a
+/// collection of recipes exposed as `recipes`.
+///
+/// A collection only has a top-level `scale` when scaling by a factor, since scaling to
+/// target servings or yield gives each recipe its own factor.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum RenderContent {
+    Recipe(RecipeContext),
+    Collection {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        scale: Option<f64>,
+        recipes: Vec<RecipeContext>,
+    },
+}
+
+/// The parts of a single recipe passed to the template
+#[derive(Debug, Serialize)]
+struct RecipeContext {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    scale: f64,
+    sections: Vec<minijinja::Value>,
+    ingredients: Vec<minijinja::Value>,
+    cookware: Vec<minijinja::Value>,
+    metadata: minijinja::Value,
+}
+
+impl RecipeContext {
+    fn new(name: Option<String>, recipe: Recipe, scale: f64) -> RecipeContext {
+        RecipeContext {
+            name,
+            scale,
+            sections: Section::from_recipe_sections(&recipe)
+                .into_iter()
+                .map(minijinja::Value::from_object)
+                .collect(),
+            ingredients: recipe
+                .ingredients
+                .into_iter()
+                .map(Ingredient::from)
+                .map(minijinja::Value::from)
+                .collect(),
+            cookware: recipe
+                .cookware
+                .into_iter()
+                .map(Cookware::from)
+                .map(minijinja::Value::from)
+                .collect(),
+            metadata: Metadata::from(recipe.metadata).into(),
+        }
+    }
+}
+
+/// The result of a render: the rendered text and the warnings collected while producing it.
+#[derive(Clone, Debug, Serialize)]
+pub struct RenderOutput {
+    /// The rendered report.
+    pub text: String,
+    /// Non-fatal problems found in the recipe, the aisle and pantry files, the datastore and
+    /// referenced recipes, in the order they were found.
+    pub warnings: Vec<Warning>,
+}
+
+/// A compiled template that can render many recipes.
+///
+/// Building a `Renderer` parses the template, registers every built-in and extension
+/// function and filter, and reads and parses the aisle and pantry files once. Each call to
+/// [`render()`][`Self::render`] then only has to parse and scale the recipe.
+///
+/// `Renderer` is `Send + Sync`, so a single instance can be shared across threads
+/// (e.g. behind an [`Arc`][`std::sync::Arc`]).
+///
+/// # Examples
+///
+/// ```
+/// use cooklang_reports::{Config, Renderer};
+///
+/// let config = Config::default();
+/// let renderer = Renderer::new("{% for i in ingredients %}{{ i.name }} {% endfor %}", &config).unwrap();
+///
+/// assert_eq!(renderer.render("@eggs{2}").unwrap(), "eggs ");
+/// assert_eq!(renderer.render("@flour{100%g}").unwrap(), "flour ");
+/// ```
+pub struct Renderer {
+    env: Environment<'static>,
+    scaling: Scaling,
+    datastore: Option<Value>,
+    base_path: Option<String>,
+    aisle_config: Option<Value>,
+    store_aisle_configs: BTreeMap<String, Value>,
+    stores: Vec<String>,
+    pantry_config: Option<Value>,
+    pantry: Option<Value>,
+    extra_context: BTreeMap<String, serde_json::Value>,
+    config_warnings: Vec<Warning>,
+    /// Rounding applied to every ingredient after scaling, if enabled
+    rounding: Option<PracticalRounding>,
+}
+
+impl Renderer {
+    /// Build a renderer for `template` using the options in `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TemplateError`][`Error::TemplateError`] if the template has a syntax error.
+    /// In strict mode, returns [`ConfigError`][`Error::ConfigError`] for the first problem
+    /// with the aisle or pantry files.
+    pub fn new(template: &str, config: &Config) -> Result<Self, Error> {
+        let mut config_warnings = Vec::new();
+        let pantry_config = load_pantry_config(config, &mut config_warnings);
+        let renderer = Self {
+            env: template_environment(template, config)?,
+            scaling: config.scaling.clone(),
+            datastore: LayeredDatastore::open(&config.datastore_paths).map(Value::from_object),
+            base_path: config
+                .base_path
+                .as_ref()
+                .and_then(|p| p.to_str())
+                .map(String::from),
+            aisle_config: config
+                .aisle_path
+                .as_ref()
+                .and_then(|path| load_aisle_config(path, None, &mut config_warnings)),
+            store_aisle_configs: config
+                .store_aisle_paths
+                .iter()
+                .filter_map(|(store, path)| {
+                    let aisle_config = load_aisle_config(path, Some(store), &mut config_warnings)?;
+                    Some((store.clone(), aisle_config))
+                })
+                .collect(),
+            stores: config.store_aisle_paths.keys().cloned().collect(),
+            pantry: pantry_config.as_deref().map(PantryConfig::sections),
+            pantry_config: pantry_config.map(Value::from_dyn_object),
+            extra_context: config.extra_context.clone(),
+            config_warnings,
+            rounding: config
+                .practical_rounding
+                .then(|| PracticalRounding::new(&config.rounding_rules)),
+        };
+        if config.strict
+            && let Some(warning) = renderer.config_warnings.first()
+        {
+            return Err(Error::ConfigError(warning.clone()));
+        }
+        Ok(renderer)
+    }
+
+    /// Render a recipe to a String.
+    ///
+    /// Warnings are printed to stderr. Use [`render_with_warnings()`][`Self::render_with_warnings`]
+    /// to get them back instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RecipeParseError`][`Error::RecipeParseError`] if the recipe cannot be parsed by the
+    /// [`CooklangParser`][`cooklang::CooklangParser`].
+    ///
+    /// Returns [`TemplateError`][`Error::TemplateError`] if rendering fails.
+    pub fn render(&self, recipe: &str) -> Result<String, Error> {
+        self.render_with_warnings(recipe)
+            .map(RenderOutput::print_warnings)
+    }
+
+    /// Render a recipe, returning the text along with the warnings collected while rendering.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`render()`][`Self::render`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cooklang_reports::{Config, Renderer, WarningKind};
+    ///
+    /// let config = Config::default();
+    /// let template = "{% for aisle, items in aisled(ingredients) | items %}{{ aisle }}{% endfor %}";
+    /// let renderer = Renderer::new(template, &config).unwrap();
+    /// let output = renderer.render_with_warnings("@eggs{2}").unwrap();
+    ///
+    /// // Without an aisle file, everything ends up under "other".
+    /// assert_eq!(output.text, "other");
+    /// assert_eq!(output.warnings[0].kind, WarningKind::Aisle);
+    /// ```
+    pub fn render_with_warnings(&self, recipe: &str) -> Result<RenderOutput, Error> {
+        let mut warnings = self.config_warnings.clone();
+        let (recipe, scale) = self.prepare_recipe(recipe, &mut warnings)?;
+        self.render_content(
+            RenderContent::Recipe(RecipeContext::new(None, recipe, scale)),
+            warnings,
+        )
+    }
+
+    /// Render a collection of recipes to a String.
+    ///
+    /// Each entry of `recipes` is a pair of the recipe's name (or path) and its source. The
+    /// template receives a `recipes` list in place of the single-recipe variables; each entry
+    /// has `name`, `scale`, `sections`, `ingredients`, `cookware` and `metadata`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CollectionRecipeParseError`][`Error::CollectionRecipeParseError`] naming the
+    /// first recipe that cannot be parsed.
+    ///
+    /// Returns [`TemplateError`][`Error::TemplateError`] if rendering fails.
+    pub fn render_collection<N, S>(&self, recipes: &[(N, S)]) -> Result<String, Error>
+    where
+        N: AsRef<str>,
+        S: AsRef<str>,
+    {
+        self.render_collection_with_warnings(recipes)
+            .map(RenderOutput::print_warnings)
+    }
+
+    /// Render a collection of recipes, returning the text along with the warnings collected
+    /// while rendering. Recipe warnings name the recipe they come from.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`render_collection()`][`Self::render_collection`].
+    pub fn render_collection_with_warnings<N, S>(
+        &self,
+        recipes: &[(N, S)],
+    ) -> Result<RenderOutput, Error>
+    where
+        N: AsRef<str>,
+        S: AsRef<str>,
+    {
+        let mut warnings = self.config_warnings.clone();
+        let recipes = recipes
+            .iter()
+            .map(|(name, source)| {
+                let name = name.as_ref();
+                let mut recipe_warnings = Vec::new();
+                let (recipe, scale) = self
+                    .prepare_recipe(source.as_ref(), &mut recipe_warnings)
+                    .map_err(|e| match e {
+                        Error::RecipeParseError(report) => Error::CollectionRecipeParseError {
+                            name: name.to_string(),
+                            report,
+                        },
+                        Error::ScaleError(source) => Error::CollectionScaleError {
+                            name: name.to_string(),
+                            source,
+                        },
+                        other => other,
+                    })?;
+                warnings.extend(recipe_warnings.into_iter().map(|w| w.in_source(name)));
+                Ok(RecipeContext::new(Some(name.to_string()), recipe, scale))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let scale = match self.scaling {
+            Scaling::Factor(factor) => Some(factor),
+            Scaling::Servings(_) | Scaling::Yield { .. } => None,
+        };
+        self.render_content(RenderContent::Collection { scale, recipes }, warnings)
+    }
+
+    /// Parse and scale a recipe, adding any parser warnings to `warnings`.
+    ///
+    /// Returns the recipe along with the factor it was scaled by.
+    fn prepare_recipe(
+        &self,
+        recipe: &str,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<(Recipe, f64), Error> {
+        // Parse and validate recipe string using global parser
+        let (mut recipe, report) = get_parser().parse(recipe).into_result()?;
+
+        warnings.extend(
+            report
+                .warnings()
+                .map(|w| Warning::from_diag(WarningKind::Recipe, w)),
+        );
+
+        let scale = scale_recipe(&mut recipe, &self.scaling)?;
+        if let Some(rounding) = &self.rounding {
+            rounding.round_recipe(&mut recipe);
+        }
+        Ok((recipe, scale))
+    }
+
+    fn render_content(
+        &self,
+        content: RenderContent,
+        mut warnings: Vec<Warning>,
+    ) -> Result<RenderOutput, Error> {
+        let template_context = TemplateContext {
+            datastore: self.datastore.clone(),
+            base_path: self.base_path.clone(),
+            aisle_config: self.aisle_config.clone(),
+            store_aisle_configs: self.store_aisle_configs.clone(),
+            stores: self.stores.clone(),
+            pantry_config: self.pantry_config.clone(),
+            pantry: self.pantry.clone(),
+            content,
+        };
+        let template = self.env.get_template(BASE_TEMPLATE)?;
+
+        // Build the render context: start with the standard TemplateContext, then
+        // overlay any extras from Config::with_context. Extras win on conflict.
+        let captured = if self.extra_context.is_empty() {
+            template.render_captured(template_context)?
+        } else {
+            let base = minijinja::Value::from_serialize(&template_context);
+            let extras = minijinja::Value::from_serialize(&self.extra_context);
+            let merged = minijinja::value::merge_maps([base, extras]);
+            template.render_captured(merged)?
+        };
+
+        warnings.extend(warning::take(captured.state()));
+        Ok(RenderOutput {
+            text: captured.into_output(),
+            warnings,
+        })
+    }
+}
+
+impl RenderOutput {
+    /// Print the warnings to stderr and return the text.
+    fn print_warnings(self) -> String {
+        for warning in &self.warnings {
+            eprintln!("Warning: {warning}");
+        }
+        self.text
+    }
+}
+
+/// Scale a recipe using the global converter, returning the factor it was scaled by.
+fn scale_recipe(recipe: &mut Recipe, scaling: &Scaling) -> Result<f64, ScaleError> {
+    match scaling {
+        Scaling::Factor(factor) => {
+            recipe.scale(*factor, get_converter());
+            Ok(*factor)
+        }
+        Scaling::Servings(servings) => {
+            let base = recipe
+                .metadata
+                .servings()
+                .and_then(|s| s.as_number())
+                .ok_or(ScaleError::InvalidServings)?;
+            recipe.scale_to_servings(*servings, get_converter())?;
+            Ok(f64::from(*servings) / f64::from(base))
+        }
+        Scaling::Yield { value, unit } => {
+            // Yield metadata looks like `500%g`; cooklang validates it while scaling
+            let base = recipe
+                .metadata
+                .get("yield")
+                .and_then(|y| y.as_str())
+                .and_then(|y| y.split_once('%'))
+                .and_then(|(base, _)| base.parse::<f64>().ok());
+            recipe.scale_to_yield(*value, unit, get_converter())?;
+            base.map(|base| value / base)
+                .ok_or(ScaleError::InvalidYield)
+        }
+    }
+}
+
+/// Load and parse an aisle configuration file, the default one or a named store's
+fn load_aisle_config(
+    aisle_path: &Path,
+    store: Option<&str>,
+    warnings: &mut Vec<Warning>,
+) -> Option<Value> {
+    match std::fs::read_to_string(aisle_path) {
+        Ok(content) => {
+            let mut aisle_warnings = Vec::new();
+            let aisle_config = AisleConfig::parse(&content, &mut aisle_warnings);
+            warnings.extend(aisle_warnings.into_iter().map(|warning| match store {
+                Some(store) => warning.in_source(store),
+                None => warning,
+            }));
+
+            Some(Value::from_object(aisle_config))
+        }
+        Err(e) => {
+            let message = match store {
+                Some(store) => format!("Failed to read aisle file for store '{store}': {e}"),
+                None => format!("Failed to read aisle file: {e}"),
+            };
+            warnings.push(Warning::new(WarningKind::Aisle, message));
+            None
+        }
+    }
+}
+
+/// Load and parse the pantry configuration file if provided
+fn load_pantry_config(config: &Config, warnings: &mut Vec<Warning>) -> Option<Arc<PantryConfig>> {
+    let pantry_path = config.pantry_path.as_ref()?;
+    match std::fs::read_to_string(pantry_path) {
+        Ok(content) => Some(Arc::new(PantryConfig::parse(&content, warnings))),
+        Err(e) => {
+            warnings.push(Warning::new(
+                WarningKind::Pantry,
+                format!("Failed to read pantry file: {e}"),
+            ));
+            None
+        }
+    }
+}
+
+/// Load a template referenced by `name` from `source`, for `{% include %}` and friends.
+fn load_template(source: &TemplateSource, name: &str) -> Result<Option<String>, minijinja::Error> {
+    match source {
+        TemplateSource::Map(templates) => Ok(templates.get(name).cloned()),
+        TemplateSource::Dir(dir) => {
+            match std::fs::read_to_string(resolve_template_path(dir, name)?) {
+                Ok(content) => Ok(Some(content)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(minijinja::Error::new(
+                    minijinja::ErrorKind::InvalidOperation,
+                    format!("could not read template '{name}'"),
+                )
+                .with_source(e)),
+            }
+        }
+    }
+}
+
+/// Resolve a template name relative to `dir`, rejecting names that would escape it.
+fn resolve_template_path(dir: &Path, name: &str) -> Result<PathBuf, minijinja::Error> {
+    let mut path = dir.to_path_buf();
+    for segment in name.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => return Err(template_escape_error(name)),
+            _ if segment.contains(':') => return Err(template_escape_error(name)),
+            _ => path.push(segment),
+        }
+    }
+    Ok(path)
+}
+
+fn template_escape_error(name: &str) -> minijinja::Error {
+    minijinja::Error::new(
+        minijinja::ErrorKind::InvalidOperation,
+        format!("template '{name}' is outside the template directory"),
+    )
+}
+
+/// Build an environment for the given template, registering built-in and extension functions.
+fn template_environment(template: &str, config: &Config) -> Result<Environment<'static>, Error> {
+    let mut env = Environment::new();
+
+    // Enable debug mode for better error messages
+    env.set_debug(true);
+
+    if config.strict {
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.add_global(warning::STRICT_GLOBAL, true);
+    }
+
+    env.add_template_owned(BASE_TEMPLATE, template.to_owned())?;
+    if let Some(source) = config.templates.clone() {
+        env.set_loader(move |name| load_template(&source, name));
+    }
+    env.add_function("db", get_from_datastore);
+    env.add_function("db_source", datastore_source);
+    env.add_function("db_exists", datastore_exists);
+    env.add_function("db_keys", datastore_keys);
+    env.add_function("get_ingredient_list", get_ingredient_list);
+    env.add_function("aisled", aisled);
+    env.add_function("excluding_pantry", excluding_pantry);
+    env.add_function("from_pantry", from_pantry);
+    env.add_function("still_needed", still_needed);
+    env.add_function("expiring_pantry", expiring_pantry);
+    env.add_function("low_stock_pantry", low_stock_pantry);
+    env.add_function("nutrition", nutrition);
+    env.add_function("cost", cost);
+
+    // Number formatting functions (also available as filters)
+    env.add_function("number_to_currency", number_to_currency);
+    env.add_function("number_to_human", number_to_human);
+    env.add_function("number_to_human_size", number_to_human_size);
+    env.add_function("number_to_percentage", number_to_percentage);
+    env.add_function("number_with_delimiter", number_with_delimiter);
+    env.add_function("number_with_precision", number_with_precision);
+
+    // Also register as filters
+    env.add_filter("number_to_currency", number_to_currency);
+    env.add_filter("number_to_human", number_to_human);
+    env.add_filter("number_to_human_size", number_to_human_size);
+    env.add_filter("number_to_percentage", number_to_percentage);
+    env.add_filter("number_with_delimiter", number_with_delimiter);
+    env.add_filter("number_with_precision", number_with_precision);
+
+    env.add_filter("numeric", numeric_filter);
+    env.add_filter("format_price", format_price_filter);
+
+    // Unit conversion filters (also available as functions)
+    env.add_filter("convert", convert_filter);
+    env.add_filter("to_system", to_system_filter);
+    env.add_function("convert", convert_filter);
+    env.add_function("to_system", to_system_filter);
+
+    // Quantity arithmetic, since templates can't use operators on quantities
+    env.add_filter("mul", mul_filter);
+    env.add_filter("add", add_filter);
+
+    // Kitchen-friendly quantity display
+    env.add_filter("fraction", fraction_filter);
+    env.add_filter("kitchen", fraction_filter);
+    let rounding = PracticalRounding::new(&config.rounding_rules);
+    env.add_filter("practical", move |value: &Value| {
+        rounding.round_value(value)
+    });
+
+    // String transformation filters (also available as functions)
+    env.add_filter("camelize", camelize_filter);
+    env.add_filter("underscore", underscore_filter);
+    env.add_filter("dasherize", dasherize_filter);
+    env.add_filter("humanize", humanize_filter);
+    env.add_filter("titleize", titleize_filter);
+    env.add_filter("upcase_first", upcase_first_filter);
+
+    // Also register as functions for direct calls
+    env.add_function("camelize", camelize_filter);
+    env.add_function("underscore", underscore_filter);
+    env.add_function("dasherize", dasherize_filter);
+    env.add_function("humanize", humanize_filter);
+    env.add_function("titleize", titleize_filter);
+    env.add_function("upcase_first", upcase_first_filter);
+
+    // Register any consumer-provided extensions
+    for ext in &config.extensions {
+        ext.register(&mut env);
+    }
+
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    fn get_test_data_path() -> PathBuf {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test");
+        path.push("data");
+        path
+    }
+
+    #[test]
+    fn renderer_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Renderer>();
+    }
+
+    #[test]
+    fn renders_many_recipes() {
+        let template = indoc! {"
+            {%- for ingredient in ingredients %}
+            - {{ ingredient.name }}: {{ ingredient.quantity }}
+            {%- endfor %}
+        "};
+        let config = Config::builder().scale(2.0).build();
+        let renderer = Renderer::new(template, &config).unwrap();
+
+        assert_eq!(renderer.render("@eggs{2}").unwrap(), "\n- eggs: 4");
+        assert_eq!(
+            renderer.render("@flour{100%g} and @milk{1%l}").unwrap(),
+            "\n- flour: 200 g\n- milk: 2 l"
+        );
+    }
+
+    #[test]
+    fn preloads_aisle_and_pantry() {
+        let config = Config::builder()
+            .aisle_path(get_test_data_path().join("aisle.conf"))
+            .pantry_path(get_test_data_path().join("pantry.conf"))
+            .build();
+        let template = indoc! {"
+            {%- for aisle, items in aisled(excluding_pantry(ingredients)) | items -%}
+            {{ aisle }}:{% for i in items %} {{ i.name }}{% endfor %};
+            {%- endfor %}
+        "};
+        let renderer = Renderer::new(template, &config).unwrap();
+
+        assert_eq!(
+            renderer.render("@milk{1%l} @flour{1%kg}").unwrap(),
+            "dairy: milk;"
+        );
+        assert_eq!(
+            renderer.render("@eggs{2} @sugar{1%tbsp}").unwrap(),
+            "dairy: eggs;sweeteners: sugar;"
+        );
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let config = Config::builder().build();
+        let renderer = Arc::new(Renderer::new("{{ ingredients[0].name }}", &config).unwrap());
+
+        let handles: Vec<_> = ["eggs", "milk", "flour"]
+            .into_iter()
+            .map(|name| {
+                let renderer = Arc::clone(&renderer);
+                std::thread::spawn(move || renderer.render(&format!("@{name}{{1}}")).unwrap())
+            })
+            .collect();
+
+        let results: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(results, ["eggs", "milk", "flour"]);
+    }
+
+    #[test]
+    fn recipe_warning_has_span() {
+        let config = Config::builder().build();
+        let renderer = Renderer::new("{{ ingredients[0].name }}", &config).unwrap();
+        let output = renderer.render_with_warnings("@eggs{2%}").unwrap();
+
+        assert_eq!(output.text, "eggs");
+        assert_eq!(
+            output.warnings,
+            [Warning {
+                kind: WarningKind::Recipe,
+                message: "Empty quantity unit".to_string(),
+                span: Some(7..8),
+            }]
+        );
+    }
+
+    #[test]
+    fn template_warnings_are_per_render() {
+        let config = Config::builder()
+            .datastore_path(get_test_data_path().join("db"))
+            .build();
+        let renderer = Renderer::new("{{ db('eggs.missing.key') }}", &config).unwrap();
+
+        for _ in 0..2 {
+            let output = renderer.render_with_warnings("@eggs{2}").unwrap();
+            assert_eq!(output.warnings.len(), 1);
+            assert_eq!(output.warnings[0].kind, WarningKind::Datastore);
+            assert!(output.warnings[0].message.contains("eggs.missing.key"));
+        }
+    }
+
+    #[test]
+    fn config_warnings_are_reported() {
+        let config = Config::builder()
+            .pantry_path(get_test_data_path().join("missing-pantry.conf"))
+            .build();
+        let renderer = Renderer::new("ok", &config).unwrap();
+        let output = renderer.render_with_warnings("@eggs{2}").unwrap();
+
+        assert_eq!(output.text, "ok");
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].kind, WarningKind::Pantry);
+        assert_eq!(output.warnings[0].span, None);
+    }
+
+    #[test]
+    fn unparsable_configs_are_reported_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let aisle_path = dir.path().join("aisle.conf");
+        let pantry_path = dir.path().join("pantry.conf");
+        std::fs::write(&aisle_path, "milk").unwrap();
+        std::fs::write(&pantry_path, "[dairy\nmilk = ").unwrap();
+        let config = Config::builder()
+            .aisle_path(&aisle_path)
+            .pantry_path(&pantry_path)
+            .build();
+
+        let template = indoc! {"
+            {{- aisled(ingredients) | list | join(',') }}
+            {{ aisled(excluding_pantry(ingredients)) | list | join(',') }}
+            {{ from_pantry(ingredients) | length }} {{ still_needed(ingredients) | length }}"};
+        let renderer = Renderer::new(template, &config).unwrap();
+        let output = renderer.render_with_warnings("@milk{1%l}").unwrap();
+
+        assert_eq!(output.text, "other\nother\n0 1");
+        // Each file's problems are reported once, however many functions use it
+        let messages: Vec<_> = output.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{messages:?}");
+        assert!(messages[0].starts_with("Error parsing input: TOML parse error"));
+        assert_eq!(
+            messages[1..],
+            [
+                "Failed to parse pantry configuration. Treating the pantry as empty.",
+                "Ingredient found before any category",
+            ]
+        );
+    }
+
+    #[test]
+    fn datastore_files_are_read_once() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("eggs")).unwrap();
+        let meta_path = dir.path().join("eggs").join("meta.yml");
+        std::fs::write(&meta_path, "density: 1.03").unwrap();
+        let config = Config::builder().datastore_path(dir.path()).build();
+        let renderer = Renderer::new("{{ db('eggs.meta.density') }}", &config).unwrap();
+
+        assert_eq!(renderer.render("@eggs{2}").unwrap(), "1.03");
+        std::fs::write(&meta_path, "density: 2.0").unwrap();
+        assert_eq!(renderer.render("@eggs{2}").unwrap(), "1.03");
+    }
+
+    #[test]
+    fn strict_config_warnings_are_errors() {
+        let config = Config::builder()
+            .pantry_path(get_test_data_path().join("missing-pantry.conf"))
+            .strict(true)
+            .build();
+        let Err(err) = Renderer::new("ok", &config) else {
+            panic!("expected a config error");
+        };
+
+        let Error::ConfigError(warning) = err else {
+            panic!("expected a config error, got {err:?}");
+        };
+        assert_eq!(warning.kind, WarningKind::Pantry);
+    }
+
+    #[test_case("{{ missing }}" ; "undefined variable")]
+    #[test_case("{{ metadata.missing }}" ; "undefined attribute")]
+    #[test_case("{{ aisled(ingredients) }}" ; "aisled without aisle file")]
+    #[test_case("{{ excluding_pantry(ingredients) }}" ; "excluding_pantry without pantry file")]
+    #[test_case("{{ low_stock_pantry() }}" ; "low_stock_pantry without pantry file")]
+    fn strict_rendering_errors(template: &str) {
+        let lenient = Renderer::new(template, &Config::default()).unwrap();
+        assert!(lenient.render_with_warnings("@eggs{2}").is_ok());
+
+        let config = Config::builder().strict(true).build();
+        let strict = Renderer::new(template, &config).unwrap();
+        let err = strict.render_with_warnings("@eggs{2}").unwrap_err();
+        assert!(matches!(err, Error::TemplateError(_)), "{err:?}");
+    }
+
+    #[test]
+    fn unreadable_store_aisle_file_names_store() {
+        let config = Config::builder()
+            .aisle_config("costco", "does-not-exist.conf")
+            .build();
+        let renderer = Renderer::new("{{ aisled(ingredients, store='costco') }}", &config).unwrap();
+        let output = renderer.render_with_warnings("@eggs{2}").unwrap();
+
+        assert!(
+            output.warnings.iter().any(|w| w.message
+                == "Aisle configuration for store 'costco' could not be loaded. All ingredients will be placed under 'other' category."),
+            "{:?}",
+            output.warnings
+        );
+    }
+
+    #[test]
+    fn collection_warnings_name_recipe() {
+        let config = Config::builder().build();
+        let renderer = Renderer::new("{{ recipes | length }}", &config).unwrap();
+        let output = renderer
+            .render_collection_with_warnings(&[("Good", "@eggs{2}"), ("Lenient", "@eggs{=2}")])
+            .unwrap();
+
+        assert_eq!(output.text, "2");
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(
+            output.warnings[0].message,
+            "in 'Lenient': Unnecessary scaling lock modifier"
+        );
+    }
+
+    #[test]
+    fn includes_and_extends_from_template_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("partials")).unwrap();
+        std::fs::write(
+            dir.path().join("layout.jinja"),
+            "<{% block body %}{% endblock %}>",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("partials").join("list.jinja"),
+            "{% for i in ingredients %}{{ i.name }};{% endfor %}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("macros.jinja"),
+            "{% macro shout(s) %}{{ s | upper }}{% endmacro %}",
+        )
+        .unwrap();
+
+        let config = Config::builder().template_dir(dir.path()).build();
+        let template = indoc! {r#"
+            {%- extends "layout.jinja" -%}
+            {%- import "macros.jinja" as m -%}
+            {%- block body %}{% include "partials/list.jinja" %}{{ m.shout("done") }}{% endblock -%}
+        "#};
+        let renderer = Renderer::new(template, &config).unwrap();
+
+        assert_eq!(
+            renderer.render("@eggs{2} @milk{1%l}").unwrap(),
+            "<eggs;milk;DONE>"
+        );
+    }
+
+    #[test]
+    fn includes_from_template_map() {
+        let config = Config::builder()
+            .templates([("header", "# {{ metadata.title }}")])
+            .build();
+        let renderer = Renderer::new("{% include 'header' %}!", &config).unwrap();
+
+        assert_eq!(
+            renderer
+                .render("---\ntitle: Toast\n---\n@bread{1}")
+                .unwrap(),
+            "# Toast!"
+        );
+    }
+
+    #[test_case("../secret.jinja"; "parent directory")]
+    #[test_case("partials/../../secret.jinja"; "nested parent directory")]
+    #[test_case("..\\\\secret.jinja"; "backslash")]
+    fn rejects_template_outside_dir(name: &str) {
+        let root = tempfile::tempdir().unwrap();
+        let templates = root.path().join("templates");
+        std::fs::create_dir(&templates).unwrap();
+        std::fs::write(root.path().join("secret.jinja"), "secret").unwrap();
+
+        let config = Config::builder().template_dir(&templates).build();
+        let renderer = Renderer::new(&format!("{{% include '{name}' %}}"), &config).unwrap();
+        let err = renderer.render("@eggs{2}").unwrap_err();
+
+        assert!(format!("{err:#?}").contains("outside the template directory"));
+    }
+
+    #[test]
+    fn collection_scales_each_recipe_to_target() {
+        let config = Config::builder().target_servings(4).build();
+        let renderer = Renderer::new(
+            "{% if scale is undefined %}per recipe{% endif %}{% for r in recipes %} {{ r.name }}={{ r.scale }}{% endfor %}",
+            &config,
+        )
+        .unwrap();
+        let recipes = [
+            ("Two", "---\nservings: 2\n---\n@eggs{1}"),
+            ("Eight", "---\nservings: 8\n---\n@eggs{4}"),
+        ];
+
+        assert_eq!(
+            renderer.render_collection(&recipes).unwrap(),
+            "per recipe Two=2.0 Eight=0.5"
+        );
+
+        let err = renderer
+            .render_collection(&[("Unknown", "@eggs{1}")])
+            .unwrap_err();
+        assert!(matches!(err, Error::CollectionScaleError { name, .. } if name == "Unknown"));
+    }
+
+    #[test]
+    fn renders_collection() {
+        let template = indoc! {"
+            {%- for recipe in recipes %}
+            # {{ recipe.metadata.title }} ({{ recipe.name }})
+            {%- for ingredient in recipe.ingredients %}
+            - {{ ingredient.name }}: {{ ingredient.quantity }}
+            {%- endfor %}
+            {%- endfor %}
+        "};
+        let recipes = [
+            ("Omelette.cook", "---\ntitle: Omelette\n---\nBeat @eggs{3}."),
+            (
+                "Toast.cook",
+                "---\ntitle: Toast\n---\nToast @bread{2%slices}.",
+            ),
+        ];
+        let config = Config::builder().scale(2.0).build();
+        let renderer = Renderer::new(template, &config).unwrap();
+
+        let expected = indoc! {"
+
+            # Omelette (Omelette.cook)
+            - eggs: 6
+            # Toast (Toast.cook)
+            - bread: 4 slices"};
+        assert_eq!(renderer.render_collection(&recipes).unwrap(), expected);
+    }
+
+    #[test]
+    fn collection_sections_and_cookware() {
+        let template =
+            "{% for r in recipes %}{{ r.sections[0][0] }}|{{ r.cookware[0].name }};{% endfor %}";
+        let recipes = [("a", "Heat #pan{}."), ("b", "Fill #pot{}.")];
+        let renderer = Renderer::new(template, &Config::default()).unwrap();
+
+        assert_eq!(
+            renderer.render_collection(&recipes).unwrap(),
+            "1. Heat pan.|pan;1. Fill pot.|pot;"
+        );
+    }
+
+    #[test]
+    fn collection_parse_error_names_recipe() {
+        let recipes = [("good", "@eggs{2}"), ("bad", "Wait ~{}.")];
+        let renderer = Renderer::new("{{ recipes | length }}", &Config::default()).unwrap();
+
+        match renderer.render_collection(&recipes) {
+            Err(Error::CollectionRecipeParseError { name, .. }) => assert_eq!(name, "bad"),
+            other => panic!("expected a collection parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn syntax_error_on_construction() {
+        let config = Config::default();
+        let result = Renderer::new("{% for x in ingredients %}", &config);
+        assert!(matches!(result, Err(Error::TemplateError(_))));
+    }
+}
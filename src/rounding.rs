@@ -0,0 +1,242 @@
+//! Practical rounding of scaled quantities to sizes that can actually be measured.
+//!
+//! Scaling a recipe gives quantities like `2.6666` eggs or `13.3 tsp`. Practical rounding
+//! rounds each quantity to a step that fits its unit, like whole eggs, the nearest 1/4 tsp
+//! or 5 g, and moves large amounts to a bigger unit, like 16 tbsp to 1 cup.
+//!
+//! Enable it for every ingredient with
+//! [`ConfigBuilder::practical_rounding()`][`crate::config::ConfigBuilder::practical_rounding`],
+//! or round single quantities in a template with the `practical` filter. Change or add rules
+//! per unit with [`ConfigBuilder::rounding_rule()`][`crate::config::ConfigBuilder::rounding_rule`].
+use crate::filters::convert::convert_quantity;
+use crate::model::{
+    GroupedQuantity, IngredientListItem, Quantity, parse_text_value, quantity_from_value,
+};
+use crate::parser::get_unit_converter;
+use cooklang::Recipe;
+use cooklang::convert::ConvertTo;
+use cooklang::quantity::{
+    GroupedQuantity as CooklangGroupedQuantity, Quantity as CooklangQuantity,
+    Value as QuantityValue,
+};
+use minijinja::{Error, ErrorKind::InvalidOperation, Value};
+use std::collections::BTreeMap;
+
+/// How quantities in one unit are rounded.
+///
+/// # Examples
+///
+/// ```
+/// use cooklang_reports::rounding::RoundingRule;
+///
+/// // Nearest 1/2 tbsp, and cups from 16 tbsp up
+/// let tbsp = RoundingRule::nearest(0.5).promote(16.0, "cup");
+/// // Nearest gram below 20 g, nearest 5 g from there
+/// let grams = RoundingRule::nearest(1.0).step_from(20.0, 5.0);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoundingRule {
+    /// Rounding steps by the amount they start at, in increasing order
+    steps: Vec<(f64, f64)>,
+    /// The amount from which quantities are converted to another unit, and that unit
+    promote: Option<(f64, String)>,
+}
+
+impl RoundingRule {
+    /// Round to the nearest multiple of `step`.
+    #[must_use]
+    pub fn nearest(step: f64) -> Self {
+        Self {
+            steps: vec![(0.0, step)],
+            promote: None,
+        }
+    }
+
+    /// Round amounts of `from` and more to the nearest multiple of `step` instead.
+    #[must_use]
+    pub fn step_from(mut self, from: f64, step: f64) -> Self {
+        self.steps.push((from, step));
+        self.steps.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self
+    }
+
+    /// Convert amounts of `from` and more to `unit`, and round them with its rule.
+    #[must_use]
+    pub fn promote<U: Into<String>>(mut self, from: f64, unit: U) -> Self {
+        self.promote = Some((from, unit.into()));
+        self
+    }
+
+    /// Round a number to the step for its size, keeping amounts above zero from rounding
+    /// down to nothing.
+    fn round(&self, number: f64) -> f64 {
+        let step = self
+            .steps
+            .iter()
+            .rev()
+            .find(|(from, _)| number >= *from)
+            .or(self.steps.first())
+            .map_or(0.0, |(_, step)| *step);
+        if step <= 0.0 {
+            return number;
+        }
+        let rounded = (number / step).round() * step;
+        if rounded <= 0.0 && number > 0.0 {
+            step
+        } else {
+            rounded
+        }
+    }
+}
+
+/// The rounding rules for every unit: the defaults, plus the configured ones.
+#[derive(Clone, Debug)]
+pub(crate) struct PracticalRounding {
+    /// Rules by unit, `""` for quantities without a unit
+    rules: BTreeMap<String, RoundingRule>,
+}
+
+impl PracticalRounding {
+    /// The default rules, with `overrides` replacing or adding rules for their units.
+    pub(crate) fn new(overrides: &BTreeMap<String, RoundingRule>) -> Self {
+        let mut rules: BTreeMap<String, RoundingRule> = [
+            ("", RoundingRule::nearest(1.0)),
+            ("tsp", RoundingRule::nearest(0.25).promote(3.0, "tbsp")),
+            ("tbsp", RoundingRule::nearest(0.5).promote(16.0, "cup")),
+            ("cup", RoundingRule::nearest(0.25)),
+            ("g", RoundingRule::nearest(1.0).step_from(20.0, 5.0)),
+            ("ml", RoundingRule::nearest(1.0).step_from(20.0, 5.0)),
+            ("kg", RoundingRule::nearest(0.05)),
+            ("l", RoundingRule::nearest(0.05)),
+            ("oz", RoundingRule::nearest(0.5)),
+            ("lb", RoundingRule::nearest(0.25)),
+        ]
+        .into_iter()
+        .map(|(unit, rule)| (unit.to_string(), rule))
+        .collect();
+        rules.extend(overrides.clone());
+        Self { rules }
+    }
+
+    /// Round the quantities of every ingredient in a scaled recipe.
+    pub(crate) fn round_recipe(&self, recipe: &mut Recipe) {
+        for ingredient in &mut recipe.ingredients {
+            if let Some(quantity) = &mut ingredient.quantity {
+                *quantity = self.round(quantity);
+            }
+        }
+    }
+
+    /// Round a quantity with the rule for its unit. Quantities in units without a rule and
+    /// text values are left as they are.
+    pub(crate) fn round(&self, quantity: &CooklangQuantity) -> CooklangQuantity {
+        self.round_promoted(parse_text_value(quantity), 0)
+    }
+
+    /// Round a quantity, following at most a few promotions to avoid rules that loop.
+    fn round_promoted(&self, quantity: CooklangQuantity, promotions: usize) -> CooklangQuantity {
+        let Some(rule) = self.rule(quantity.unit().unwrap_or_default()) else {
+            return quantity;
+        };
+        let largest = match quantity.value() {
+            QuantityValue::Number(n) => n.value(),
+            QuantityValue::Range { end, .. } => end.value(),
+            QuantityValue::Text(_) => return quantity,
+        };
+
+        if let Some((from, unit)) = &rule.promote
+            && largest >= *from
+            && promotions < 4
+            && let Ok(promoted) =
+                convert_quantity(quantity.clone(), ConvertTo::from(unit.as_str()), unit)
+        {
+            // Keep the unit as the rule names it, rather than the converter's symbol for it
+            let promoted = CooklangQuantity::new(promoted.value().clone(), Some(unit.clone()));
+            return self.round_promoted(promoted, promotions + 1);
+        }
+
+        let value = match quantity.value() {
+            QuantityValue::Number(n) => QuantityValue::from(rule.round(n.value())),
+            QuantityValue::Range { start, end } => QuantityValue::Range {
+                start: rule.round(start.value()).into(),
+                end: rule.round(end.value()).into(),
+            },
+            QuantityValue::Text(_) => return quantity,
+        };
+        CooklangQuantity::new(value, quantity.unit().map(String::from))
+    }
+
+    /// Find the rule for a unit as written, or for another name of the same unit, like
+    /// `teaspoon` for `tsp`.
+    fn rule(&self, unit: &str) -> Option<&RoundingRule> {
+        if let Some(rule) = self.rules.get(unit) {
+            return Some(rule);
+        }
+        let unit = get_unit_converter().find_unit(unit)?;
+        unit.names
+            .iter()
+            .chain(&unit.symbols)
+            .chain(&unit.aliases)
+            .find_map(|name| self.rules.get(name.as_ref()))
+    }
+
+    /// Round a quantity, an item from `get_ingredient_list` or its `quantities` in a
+    /// template, for the `practical` filter.
+    pub(crate) fn round_value(&self, value: &Value) -> Result<Value, Error> {
+        let grouped = value
+            .downcast_object_ref::<IngredientListItem>()
+            .map(|item| &item.quantities)
+            .or_else(|| value.downcast_object_ref::<GroupedQuantity>());
+        if let Some(grouped) = grouped {
+            let mut rounded = CooklangGroupedQuantity::empty();
+            for quantity in grouped.to_cooklang() {
+                rounded.add(&self.round(&quantity), get_unit_converter());
+            }
+            return Ok(Value::from(GroupedQuantity::from(rounded)));
+        }
+
+        let quantity = quantity_from_value(value).map_err(|e| Error::new(InvalidOperation, e))?;
+        Ok(Value::from(Quantity::from(self.round(&quantity))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("2.6666", None, "3"; "whole eggs")]
+    #[test_case("0.3", None, "1"; "never rounds to nothing")]
+    #[test_case("0.6", Some("tsp"), "0.5 tsp"; "quarter teaspoon")]
+    #[test_case("13.3", Some("tsp"), "4.5 tbsp"; "teaspoons promoted to tablespoons")]
+    #[test_case("16", Some("tbsp"), "1 cup"; "tablespoons promoted to cups")]
+    #[test_case("1.3", Some("teaspoon"), "1.25 teaspoon"; "unit by another name")]
+    #[test_case("13.3", Some("g"), "13 g"; "small grams")]
+    #[test_case("133.3", Some("g"), "135 g"; "nearest five grams")]
+    #[test_case("1.2-2.6", None, "1-3"; "range")]
+    #[test_case("a pinch", None, "a pinch"; "text")]
+    #[test_case("1.3", Some("clove"), "1.3 clove"; "unit without a rule")]
+    fn rounds(value: &str, unit: Option<&str>, expected: &str) {
+        let rounding = PracticalRounding::new(&BTreeMap::new());
+        // Text values are parsed as numbers before rounding
+        let quantity = CooklangQuantity::new(value.to_string().into(), unit.map(String::from));
+        assert_eq!(expected, rounding.round(&quantity).to_string());
+    }
+
+    #[test]
+    fn configured_rules_replace_defaults() {
+        let overrides = BTreeMap::from([
+            (String::new(), RoundingRule::nearest(0.5)),
+            ("clove".to_string(), RoundingRule::nearest(1.0)),
+        ]);
+        let rounding = PracticalRounding::new(&overrides);
+        let round = |value: &str, unit: Option<&str>| {
+            let quantity = CooklangQuantity::new(value.to_string().into(), unit.map(String::from));
+            rounding.round(&quantity).to_string()
+        };
+
+        assert_eq!(round("2.6666", None), "2.5");
+        assert_eq!(round("1.3", Some("clove")), "1 clove");
+        assert_eq!(round("0.6", Some("tsp")), "0.5 tsp");
+    }
+}
@@ -0,0 +1,110 @@
+//! Warnings collected while rendering a report.
+//!
+//! Problems that don't stop a report from rendering, like a recipe parser warning or a key
+//! missing from the datastore, are collected as [`Warning`]s and returned alongside the
+//! rendered text in a [`RenderOutput`][`crate::renderer::RenderOutput`].
+use minijinja::State;
+use serde::Serialize;
+use std::fmt::Display;
+use std::ops::Range;
+use std::sync::{Mutex, PoisonError};
+
+/// Name of the template temp holding the warnings of the current render.
+const WARNINGS_TEMP: &str = "__cooklang_reports_warnings";
+
+/// Name of the global that is set when rendering in strict mode.
+pub(crate) const STRICT_GLOBAL: &str = "__cooklang_reports_strict";
+
+/// What a [`Warning`] is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// The recipe being rendered.
+    Recipe,
+    /// The aisle configuration file.
+    Aisle,
+    /// The pantry configuration file.
+    Pantry,
+    /// A lookup in the datastore.
+    Datastore,
+    /// A recipe referenced from the recipe being rendered.
+    Reference,
+}
+
+/// A non-fatal problem found while rendering a report.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    /// What the warning is about.
+    pub kind: WarningKind,
+    /// Human-readable description of the problem.
+    pub message: String,
+    /// Byte range of the problem in the source it comes from (the recipe, the aisle or pantry
+    /// file, or the referenced recipe), if known.
+    pub span: Option<Range<usize>>,
+}
+
+impl Warning {
+    pub(crate) fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Build a warning from a cooklang diagnostic, keeping the span of its main label.
+    pub(crate) fn from_diag(kind: WarningKind, diag: &cooklang::error::SourceDiag) -> Self {
+        Self {
+            kind,
+            message: diag.to_string(),
+            span: diag.labels.first().map(|(span, _)| span.range()),
+        }
+    }
+
+    /// Prefix the message with where the warning comes from.
+    pub(crate) fn in_source(mut self, source: &str) -> Self {
+        self.message = format!("in '{source}': {}", self.message);
+        self
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Warnings emitted by functions and filters during a single render.
+#[derive(Debug, Default)]
+struct WarningCollector(Mutex<Vec<Warning>>);
+
+impl minijinja::value::Object for WarningCollector {}
+
+/// Record a warning for the render `state` belongs to.
+pub(crate) fn emit(state: &State, warning: Warning) {
+    state
+        .get_or_set_temp_object(WARNINGS_TEMP, WarningCollector::default)
+        .0
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(warning);
+}
+
+/// Whether the render `state` belongs to is in strict mode, where some warnings are errors.
+pub(crate) fn is_strict(state: &State) -> bool {
+    state
+        .lookup(STRICT_GLOBAL)
+        .is_some_and(|strict| strict.is_true())
+}
+
+/// Take the warnings recorded for the render `state` belongs to.
+pub(crate) fn take(state: &State) -> Vec<Warning> {
+    state
+        .get_temp(WARNINGS_TEMP)
+        .and_then(|value| value.downcast_object::<WarningCollector>())
+        .map(|collector| {
+            std::mem::take(&mut *collector.0.lock().unwrap_or_else(PoisonError::into_inner))
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,181 @@
+// Integration test: the crate's `missing_docs` and `unwrap_used` lints are meant
+// for the library surface, not for test scaffolding.
+#![allow(missing_docs)]
+#![allow(clippy::unwrap_used)]
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn data_path(parts: &[&str]) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("test");
+    path.push("data");
+    path.extend(parts);
+    path
+}
+
+fn cooklang_reports(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cooklang-reports"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The binary may exit before reading stdin, e.g. on a missing template, so a broken
+    // pipe here is expected
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn renders_recipe_file() {
+    let template = data_path(&["reports", "ingredients.md.jinja"]);
+    let recipe = data_path(&["recipes", "Pancakes.cook"]);
+    let output = cooklang_reports(
+        &[
+            "--template",
+            template.to_str().unwrap(),
+            "--scale",
+            "2",
+            recipe.to_str().unwrap(),
+        ],
+        "",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "# Ingredients Report\n\n* eggs: 6 large\n* flour: 250 g\n* milk: 500 ml"
+    );
+}
+
+#[test]
+fn renders_stdin_with_context_to_output_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("report.jinja");
+    std::fs::write(
+        &template,
+        "{{ who }} x{{ servings }}: {{ ingredients[0].name }}",
+    )
+    .unwrap();
+    let out = dir.path().join("report.txt");
+
+    let output = cooklang_reports(
+        &[
+            "--template",
+            template.to_str().unwrap(),
+            "--context",
+            "who=Sam",
+            "--context",
+            "servings=4",
+            "--output",
+            out.to_str().unwrap(),
+        ],
+        "@eggs{2}",
+    );
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(std::fs::read_to_string(out).unwrap(), "Sam x4: eggs");
+}
+
+#[test]
+fn template_error_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("broken.jinja");
+    std::fs::write(&template, "{% for i in ingredients %}").unwrap();
+
+    let output = cooklang_reports(&["--template", template.to_str().unwrap()], "@eggs{2}");
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("Hint: This is a syntax error")
+    );
+}
+
+#[test]
+fn recipe_error_exit_code() {
+    let template = data_path(&["reports", "ingredients.md.jinja"]);
+    let output = cooklang_reports(&["--template", template.to_str().unwrap()], "Wait ~{}.");
+
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn missing_template_exit_code() {
+    let output = cooklang_reports(&["--template", "does-not-exist.jinja"], "@eggs{2}");
+
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn strict_config_error_exit_code() {
+    let template = data_path(&["reports", "ingredients.md.jinja"]);
+    let output = cooklang_reports(
+        &[
+            "--template",
+            template.to_str().unwrap(),
+            "--aisle",
+            "does-not-exist.conf",
+            "--strict",
+        ],
+        "@eggs{2}",
+    );
+
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn scales_to_servings() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("report.jinja");
+    std::fs::write(&template, "{{ scale }} {{ ingredients[0].quantity }}").unwrap();
+    let recipe = data_path(&["recipes", "Recipe With Servings.cook"]);
+
+    let output = cooklang_reports(
+        &[
+            "--template",
+            template.to_str().unwrap(),
+            "--servings",
+            "8",
+            recipe.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2.0 400 g");
+
+    let output = cooklang_reports(
+        &["--template", template.to_str().unwrap(), "--servings", "8"],
+        "@eggs{2}",
+    );
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn aisles_per_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("report.jinja");
+    std::fs::write(
+        &template,
+        "{% for store in stores %}{{ store }}: {{ aisled(ingredients, store=store) | list | join(', ') }}{% endfor %}",
+    )
+    .unwrap();
+    let store = format!("costco={}", data_path(&["costco.conf"]).display());
+
+    let output = cooklang_reports(
+        &["--template", template.to_str().unwrap(), "--store", &store],
+        "@eggs{2} @flour{1%kg}",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "costco: bulk, fridge"
+    );
+}